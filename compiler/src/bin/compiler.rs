@@ -2,7 +2,7 @@ use std::{ ffi::OsStr, io::{ Read, Write }, fs::File, path::Path };
 use log;
 use simple_logger;
 use clap::Parser;
-use virtmach::{ VirtMach, VMAtom, Program, ListingError };
+use virtmach::{ Atom, VirtMach, VMAtom, Program, REG_MAX, ListingError, interrupts::{ SoftInterruptDef, SoftInterruptFunction } };
 
 #[derive(Parser, Debug)]
 #[command(name = "virtmach-rs Compiler")]
@@ -16,6 +16,12 @@ struct Args {
     #[arg(value_delimiter = ' ', num_args = 1.., help = "Additional interrupts to include. Reads from a .csv file by the same name")]
     interrupts: Option<Vec<String>>,
     
+    #[arg(short, long, value_parser = ["i8", "i16", "i32", "i64", "u8", "u16", "u32"], help = "Atom type of the target VM, defaults to the library's VMAtom")]
+    atom: Option<String>,
+
+    #[arg(short, long, default_value_t = REG_MAX as u8, value_parser = clap::value_parser!(u8).range(1..=REG_MAX as i64), help = "Register count of the target VM")]
+    regs: u8,
+
     #[arg(short, long, help = "Optional binary output file")]
     output: Option<String>,
 
@@ -35,18 +41,22 @@ fn main() -> Result<(), ()> {
         }).unwrap()
     }
     
-    let mut external_interrupts = Vec::<(String, String)>::new();
+    let mut external_interrupts = Vec::<(String, Vec<(u8, String, usize, usize)>)>::new();
     for int_name in args.interrupts.unwrap_or(vec![]) {        
         let filename = format!("{}.csv", int_name);
         match File::open(&filename) {
             Ok(mut file) => {
                 let mut content = String::new();
-                external_interrupts.push((int_name, match file.read_to_string(&mut content) { Ok(_) => { content }, _ => { String::new() }  }));
+                let functions = match file.read_to_string(&mut content) { Ok(_) => { parse_functions(&content) }, _ => { vec![] }  };
+                external_interrupts.push((int_name, functions));
             }
             Err(_) => { eprintln!(); eprintln!("[ERROR] could not load {}", &filename); eprintln!(); }
         }        
     }  
 
+    let functions: Vec<Vec<SoftInterruptFunction>> = external_interrupts.iter().map(|(_, functions)| functions.iter().map(|(no, name, arguments, returns)| SoftInterruptFunction::new(*no, name, *arguments, *returns, "")).collect()).collect();
+    let external_interrupts: Vec<SoftInterruptDef> = external_interrupts.iter().zip(&functions).map(|((name, _), functions)| SoftInterruptDef { name, functions }).collect();
+
     let out_file = args.output.unwrap_or(format!("{}.bin", Path::new(&args.source).file_stem().unwrap_or(OsStr::new("out")).to_str().unwrap_or("out")));
 
     match File::open(&args.source) {
@@ -55,42 +65,16 @@ fn main() -> Result<(), ()> {
             let mut content = String::new();
             match file.read_to_string(&mut content) {
                 Ok(_) => {
-                    match VirtMach::compile(&name, &content, external_interrupts) {
-                        Ok(res) => {                    
-                            let program = res.0;
-                            if args.verbose > 0 {
-                                disassemble(&program);   
-                            }
-
-                            let mut file = File::create(out_file);
-                            if file.is_ok() {
-                                file.unwrap().write(&program.data);
-                            }
-
-                            Ok(())                                                      
-                        }
-                        Err(err) => {
-                            let mut line: Option<usize> = None;
-                            if args.verbose > 0 { match err {                            
-                                ListingError::IllegalOp(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal op code: {}", e); },
-                                ListingError::IllegalArgument(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal argument: {}", e); },
-                                ListingError::IllegalRegister(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal register: {}", e); },
-                                ListingError::MalformedDefine(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] malformed define: {}", e); },
-                                ListingError::IllegalDefineValue(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal value in define: {}", e); },
-                                ListingError::UnknownLabel(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown label: {}", e); },
-                                ListingError::UnknownInterrupt(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown interrupt: {}", e); },
-                                ListingError::UnknownFunction(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown function: {}", e); },                                
-                                ListingError::MalformedFunction(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] malformed function: {}", e); },    
-                                ListingError::IllegalInterrupt(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal interrupt: {}", e); },
-                                ListingError::NoError => {},                                
-                            } }
-                            if line.is_some() {
-                                let line = line.unwrap();
-                                eprintln!(); eprintln!("\tline #{}: {:?}", line, content.lines().nth(line - 1).unwrap_or("")); eprintln!();
-                            }
-                            Err(())
-                        }
-                    }                                                                       
+                    match args.atom.as_deref() {
+                        Some("i8") => compile_regs::<i8>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("i16") => compile_regs::<i16>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("i32") => compile_regs::<i32>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("i64") => compile_regs::<i64>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("u8") => compile_regs::<u8>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("u16") => compile_regs::<u16>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        Some("u32") => compile_regs::<u32>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose),
+                        _ => compile_regs::<VMAtom>(args.regs, &name, &content, &external_interrupts, &out_file, args.verbose)
+                    }
                 }
                 Err(err) => { if args.verbose > 0 { eprintln!(); eprintln!("[ERROR] could not read from file: {}", err); eprintln!(); } Err(()) }
             }
@@ -99,20 +83,88 @@ fn main() -> Result<(), ()> {
     }
 }
 
-pub fn disassemble(program: &Program) {
+/// Reads interrupt functions from lines of `no, name, arguments, returns`.
+fn parse_functions(csv: &str) -> Vec<(u8, String, usize, usize)> {
+    let mut functions = vec![];
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    for result in rdr.records() {            
+        if let Ok(record) = result {
+            if record.len() >= 4 {
+                match (record[0].trim().parse::<u8>(), record[2].trim().parse::<usize>(), record[3].trim().parse::<usize>()) {
+                    (Ok(no), Ok(arguments), Ok(returns)) => functions.push((no, String::from(record[1].trim()), arguments, returns)),
+                    _ => { eprintln!("[WARN] skipping malformed interrupt function: {:?}", record); }
+                }
+            }
+        }
+    }
+    return functions;
+}
+
+/// Picks the target's register count, which `VirtMach` takes as a const parameter.
+fn compile_regs<A: Atom>(regs: u8, name: &str, content: &str, external_interrupts: &[SoftInterruptDef], out_file: &str, verbose: u32) -> Result<(), ()> {
+    macro_rules! regs {
+        ($($n:literal)*) => { match regs { $($n => compile::<A, $n>(name, content, external_interrupts, out_file, verbose),)* _ => Err(()) } }
+    }
+    regs!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+}
+
+fn compile<A: Atom, const REGS: usize>(name: &str, content: &str, external_interrupts: &[SoftInterruptDef], out_file: &str, verbose: u32) -> Result<(), ()> {
+    match VirtMach::<A, REGS>::compile(name, content, external_interrupts) {
+        Ok(res) => {                    
+            let program = res;
+            if verbose > 0 {
+                disassemble(&program.as_program());   
+            }
+
+            let mut file = File::create(out_file);
+            if file.is_ok() {
+                file.unwrap().write(&program.data);
+            }
+
+            Ok(())                                                      
+        }
+        Err(err) => {
+            let mut line: Option<usize> = None;
+            if verbose > 0 { match err {                            
+                ListingError::IllegalOp(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal op code: {}", e); },
+                ListingError::IllegalArgument(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal argument: {}", e); },
+                ListingError::IllegalRegister(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal register: {}", e); },
+                ListingError::MalformedDefine(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] malformed define: {}", e); },
+                ListingError::IllegalDefineValue(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal value in define: {}", e); },
+                ListingError::UnknownLabel(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown label: {}", e); },
+                ListingError::LabelOutOfRange(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] label out of range: {}", e); },
+                ListingError::UnknownInterrupt(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown interrupt: {}", e); },
+                ListingError::UnknownFunction(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] unknown function: {}", e); },                                
+                ListingError::MalformedFunction(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] malformed function: {}", e); },    
+                ListingError::IllegalInterrupt(l, e) => { line = Some(l); eprintln!(); eprintln!("[ERROR] illegal interrupt: {}", e); },
+                ListingError::NoError => {},                                
+            } }
+            if line.is_some() {
+                let line = line.unwrap();
+                eprintln!(); eprintln!("\tline #{}: {:?}", line, content.lines().nth(line - 1).unwrap_or("")); eprintln!();
+            }
+            Err(())
+        }
+    }
+}
+
+pub fn disassemble<A: Atom>(program: &Program<A>) {
     println!();
-    println!("Program \"{}\" ({}b):", program.id, program.data.len());
+    println!("Program \"{}\" ({}b):", program.id, program.data().len());
     println!();
     let mut pos = 0usize;
     loop {
         let mut op = String::new();
         let addr = pos;                                        
         pos = VirtMach::decompile(&program, pos, &mut op);
-        let slice = &program.data[addr..pos];
-        let hex_wid = (1 + size_of::<VMAtom>()) * 3 - 1;
+        let slice = &program.code()[addr..pos];
+        let hex_wid = (1 + A::SIZE) * 3 - 1;
         println!("\t{:04x} | {:hex_wid$} | {}", addr, slice.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "), op.as_str());        
 
-        if pos >= program.data.len() { break }
+        if pos >= program.code().len() { break }
     }       
     println!();
 }
\ No newline at end of file
@@ -5,10 +5,10 @@ mod helpers;
 fn main(){
     match helpers::load_file("examples/programs/count.txt") {       
         Ok(content) => {
-            match VirtMach::compile(content.0.as_str(), content.1.as_str(), vec![]) {
+            match <VirtMach>::compile(content.0.as_str(), content.1.as_str(), &[]) {
                 Ok(res) => {                    
-                    let program = res.0;
-                    helpers::disassemble(program);                                                         
+                    let program = res;
+                    helpers::disassemble(program.as_program());                                                         
                 }
                 Err(err) => println!("compile error: {:?}", err)
             }
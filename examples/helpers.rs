@@ -4,18 +4,18 @@ use virtmach::{VirtMach, VMAtom, Program};
 #[allow(dead_code)]
 pub fn disassemble(program: Program) {
     println!();
-    println!("Program \"{}\" ({}b):", program.id, program.data.len());
+    println!("Program \"{}\" ({}b):", program.id, program.data().len());
     println!();
     let mut pos = 0usize;
     loop {
         let mut op = String::new();
         let addr = pos;                                        
         pos = VirtMach::decompile(&program, pos, &mut op);
-        let slice = &program.data[addr..pos];
+        let slice = &program.code()[addr..pos];
         let hex_wid = (1 + size_of::<VMAtom>()) * 3 - 1;
         println!("\t{:04x} | {:hex_wid$} | {}", addr, slice.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "), op.as_str());        
 
-        if pos >= program.data.len() { break }
+        if pos >= program.code().len() { break }
     }       
     println!();
 }
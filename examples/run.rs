@@ -1,5 +1,5 @@
 use std::{thread, time};
-use virtmach::VirtMach;
+use virtmach::{VirtMach, VMAtom};
 use virtmach::interrupts::{ SoftInterrupt, Proc, Math, Random };
 
 mod helpers;
@@ -7,15 +7,19 @@ mod helpers;
 fn main(){
     match helpers::load_file("examples/programs/count.txt") {
         Ok(content) => {            
-            match VirtMach::compile(content.0.as_str(), content.1.as_str(), vec![]) {
+            match <VirtMach>::compile(content.0.as_str(), content.1.as_str(), &[]) {
                 Ok(res) => {                    
-                    let program = res.0;
+                    let program = res;
 
-                    let mut vm = VirtMach::new();          
+                    let mut memory = [0 as VMAtom;23];
+                    let mut vm = VirtMach::new(&mut memory);          
 
-                    vm.load_program(program);
+                    let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut Random::new(0)];
 
-                    let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut Random {}];
+                    if let Err(err) = vm.load_program(program.as_program(), interrupts) {
+                        println!("load error: {:?}", err);
+                        return;
+                    }
 
                     loop {
                         vm.run(1, interrupts);
@@ -4,8 +4,8 @@ extern crate sdl2;
 use sdl2::{ event::Event, keyboard::Keycode, pixels::Color };
 
 use std::{thread, time};
-use virtmach::VirtMach;
-use virtmach::interrupts::{self, SoftInterrupt, Proc, Math, Random };
+use virtmach::{VirtMach, VMAtom};
+use virtmach::interrupts::{ SoftInterrupt, Proc, Math, Random };
 
 mod helpers;
 
@@ -18,13 +18,12 @@ const SCALE: f32 = 5.0;
 fn main() -> Result<(), String> {
     match helpers::load_file("examples/programs/primitives.txt") {
         Ok(content) => {            
-            match VirtMach::compile(content.0.as_str(), content.1.as_str(), [(String::from(interrupts::SurfaceMap.0), String::from(interrupts::SurfaceMap.1))].to_vec()) {
+            match <VirtMach>::compile(content.0.as_str(), content.1.as_str(), &[int_surface_sdl2::IntSurface::DEF]) {
                 Ok(res) => {                    
-                    let program = res.0;
+                    let program = res;
 
-                    let mut vm = VirtMach::new();
-
-                    vm.load_program(program);                                                          
+                    let mut memory: [VMAtom;23] = [0;23];
+                    let mut vm = VirtMach::new(&mut memory);
 
                     let sdl_context = sdl2::init()?;
                     let video_subsystem = sdl_context.video()?;
@@ -42,6 +41,15 @@ fn main() -> Result<(), String> {
                     canvas.set_draw_color(Color::RGB(0, 0, 0));
                     canvas.clear();
                     canvas.present();                    
+
+                    let mut random = Random::new(time::SystemTime::now().duration_since(time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+
+                    {
+                        let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut random, &mut int_surface_sdl2::IntSurface { canvas: &mut canvas, clip: [0, 0, W as i32, H as i32 ] }];
+                        if let Err(err) = vm.load_program(program.as_program(), interrupts) {
+                            return Err(format!("load error: {:?}", err));
+                        }
+                    }
     
                     let mut event_pump = sdl_context.event_pump()?;
 
@@ -59,7 +67,7 @@ fn main() -> Result<(), String> {
                             }
                         }                        
 
-                        let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut Random {}, &mut int_surface_sdl2::IntSurface { canvas: &mut canvas, clip: [0, 0, W as i32, H as i32 ] }];                            
+                        let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut random, &mut int_surface_sdl2::IntSurface { canvas: &mut canvas, clip: [0, 0, W as i32, H as i32 ] }];                            
                         
                         vm.run(1024, interrupts);
 
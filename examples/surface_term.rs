@@ -1,9 +1,9 @@
 #![allow(static_mut_refs)]
 
 use std::{thread, time};
-use virtmach::VirtMach;
+use virtmach::{VirtMach, VMAtom};
 use virtmach::interrupts::{ Math, Proc, Random };
-use virtmach::{ RuntimeError, interrupts::{ self, SoftInterrupt } };
+use virtmach::{ StopReason, interrupts::{ SoftInterrupt } };
 use bitmap_writer::{Bitmap, Writer, Frame, Style};
 
 mod helpers;
@@ -20,13 +20,12 @@ fn main(){
         Ok(content) => {
                                        
             
-            match VirtMach::compile(content.0.as_str(), content.1.as_str(), [(String::from(interrupts::SurfaceMap.0), String::from(interrupts::SurfaceMap.1))].to_vec()) {
+            match <VirtMach>::compile(content.0.as_str(), content.1.as_str(), &[int_surface_term::IntSurface::DEF]) {
                 Ok(res) => {                    
-                    let program = res.0;
+                    let program = res;
 
-                    let mut vm = VirtMach::new();
-
-                    vm.load_program(program);
+                    let mut memory: [VMAtom;23] = [0;23];
+                    let mut vm = VirtMach::new(&mut memory);
 
                     let mut w = Writer::new();
                         w.frame(Frame::UnicodeDoubleUFrame)
@@ -34,21 +33,26 @@ fn main(){
                         .ansi_position(1, 1);
                                                                 
                                 
-                    let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut Random {}, &mut int_surface_term::IntSurface { w: W as i32, h: H as i32, clip: [0, 0, W as i32, H as i32 ], bitmap: unsafe { &mut BUF } }];                            
+                    let interrupts: &mut [&mut dyn SoftInterrupt] = &mut [ &mut Proc {}, &mut Math {}, &mut Random::new(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)), &mut int_surface_term::IntSurface { w: W as i32, h: H as i32, clip: [0, 0, W as i32, H as i32 ], bitmap: unsafe { &mut BUF } }];                            
+
+                    if let Err(err) = vm.load_program(program.as_program(), interrupts) {
+                        println!("load error: {:?}", err);
+                        return;
+                    }
 
                     loop {
-                        vm.run(1024, interrupts);
+                        let reason = vm.run(1024, interrupts);
 
                         let mut dashboard = String::new();
                         vm.write_dashboard(&mut dashboard, 0b111, 6);
                         
-                        if vm.error == RuntimeError::NoError {                                    
+                        if !matches!(reason, StopReason::Fault(..)) {                                    
                             print!("\x1b[J");
                             let bitmap = Bitmap::new(W, H, unsafe { &mut BUF });                                   
                             w.print(&bitmap);
                         
                             for (i, line) in dashboard.lines().enumerate() { print!("\x1b[{};{}H {}\x1b[K", i + 1, W + 3, line); }
-                            println!("");
+                            println!();
                         } else {                                    
                             println!("{}", dashboard);
                             break;
@@ -0,0 +1,213 @@
+//! Procedural macros of `virtmach`, use them through the `virtmach` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Error, Expr, FnArg, ImplItem, ItemImpl, LitInt, LitStr, ReturnType, Type};
+
+/// Implements `SoftInterrupt` from the methods of an impl block.
+///
+/// Every method is a function of the interrupt, numbered in order from 0 or from the last
+/// `#[no(n)]`, and numbers may not repeat. Arguments are popped off the stack in declaration order and return values,
+/// an atom, a tuple of atoms or a `Result` of either with a `RuntimeError`, are pushed in
+/// order. A `&mut VirtMach` parameter receives the VM and is not counted as an argument.
+/// Doc comments become the functions' help texts.
+///
+/// ```ignore
+/// #[virtmach::interrupt(name = "led")]
+/// impl Led {
+///     /// led, brightness
+///     fn set(&mut self, led: VMAtom, brightness: VMAtom) { ... }
+///     #[no(16)]
+///     fn count(&self) -> VMAtom { ... }
+/// }
+/// ```
+///
+/// Options: `name` (defaults to the lowercase type name), `atom` (defaults to `VMAtom`)
+/// and `regs` (defaults to `REG_MAX`), the latter two may name generics of the impl block.
+/// The generated `DEF` constant is what `VirtMach::compile` expects.
+#[proc_macro_attribute]
+pub fn interrupt(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name: Option<LitStr> = None;
+    let mut atom: Option<Type> = None;
+    let mut regs: Option<Expr> = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("atom") {
+            atom = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("regs") {
+            regs = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `name`, `atom` or `regs`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+
+    let mut item = parse_macro_input!(item as ItemImpl);
+    match expand(&mut item, name, atom, regs) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+fn expand(item: &mut ItemImpl, name: Option<LitStr>, atom: Option<Type>, regs: Option<Expr>) -> Result<TokenStream2, Error> {
+    let self_ty = &item.self_ty;
+    let name = match name {
+        Some(name) => name.value(),
+        None => match &**self_ty {
+            Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string().to_lowercase()).unwrap_or_default(),
+            _ => return Err(Error::new(self_ty.span(), "cannot derive an interrupt name, use `name = \"...\"`"))
+        }
+    };
+    let atom = atom.map(|atom| quote!(#atom)).unwrap_or(quote!(::virtmach::VMAtom));
+    let regs = regs.map(|regs| quote!(#regs)).unwrap_or(quote!(::virtmach::REG_MAX));
+
+    let mut functions = vec![];
+    let mut nos = vec![];
+    let mut arms = vec![];
+    let mut no = 0u16;
+
+    for impl_item in item.items.iter_mut() {
+        let ImplItem::Fn(method) = impl_item else { continue; };
+
+        let mut help = vec![];
+        let mut err = None;
+        method.attrs.retain(|attr| {
+            if attr.path().is_ident("no") {
+                match attr.parse_args::<LitInt>().and_then(|lit| lit.base10_parse::<u16>()) {
+                    Ok(n) => no = n,
+                    Err(e) => err = Some(e)
+                }
+                return false;
+            }
+            if attr.path().is_ident("doc")
+                && let syn::Meta::NameValue(syn::MetaNameValue { value: Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }), .. }) = &attr.meta {
+                help.push(doc.value().trim().to_string());
+            }
+            true
+        });
+        if let Some(err) = err { return Err(err); }
+        if no > u8::MAX as u16 { return Err(Error::new(method.sig.ident.span(), "function numbers are limited to 255")); }
+        if nos.contains(&(no as u8)) { return Err(Error::new(method.sig.ident.span(), "duplicate function number")); }
+
+        let ident = &method.sig.ident;
+        let mut pops = vec![];
+        let mut args = vec![];
+        let mut receiver = false;
+        for input in &method.sig.inputs {
+            match input {
+                FnArg::Receiver(_) => receiver = true,
+                FnArg::Typed(pat) if is_vm(&pat.ty) => args.push(quote!(vm)),
+                FnArg::Typed(_) => {
+                    let arg = quote::format_ident!("__arg{}", pops.len());
+                    pops.push(quote!(let #arg = vm.stack_pop();));
+                    args.push(quote!(#arg));
+                }
+            }
+        }
+        if !receiver { return Err(Error::new(method.sig.span(), "interrupt functions take `&self` or `&mut self`")); }
+
+        let returns = match &method.sig.output {
+            ReturnType::Default => 0,
+            ReturnType::Type(_, ty) => count_returns(ty)
+        };
+        let push = match &method.sig.output {
+            ReturnType::Default => quote!(),
+            ReturnType::Type(_, ty) => push_returns(ty, quote!(__res))
+        };
+
+        let arguments = pops.len();
+        let fn_no = no as u8;
+        let fn_name = ident.to_string();
+        let help = help.join(" ");
+        functions.push(quote!(::virtmach::interrupts::SoftInterruptFunction::new(#fn_no, #fn_name, #arguments, #returns, #help)));
+        nos.push(fn_no);
+        arms.push(quote!({
+            #(#pops)*
+            let __res = self.#ident(#(#args),*);
+            #push
+        }));
+
+        no += 1;
+    }
+
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+
+    Ok(quote! {
+        #item
+
+        impl #impl_generics #self_ty #where_clause {
+            pub const DEF: ::virtmach::interrupts::SoftInterruptDef<'static> = ::virtmach::interrupts::SoftInterruptDef { name: #name, functions: &[
+                #(#functions),*
+            ] };
+        }
+
+        impl #impl_generics ::virtmach::interrupts::SoftInterrupt<#atom, { #regs }> for #self_ty #where_clause {
+            fn def(&self) -> ::virtmach::interrupts::SoftInterruptDef<'_> {
+                return Self::DEF;
+            }
+
+            fn name(&self) -> &str {
+                return Self::DEF.name;
+            }
+
+            #[allow(unused_variables, clippy::let_unit_value)]
+            fn call(&mut self, vm: &mut ::virtmach::VirtMach<'_, #atom, { #regs }>) {
+                let op = ::virtmach::Atom::to_i64(vm.stack_pop());
+                match u8::try_from(op) {
+                    #(Ok(#nos) => #arms)*
+                    _ => { vm.error = ::virtmach::RuntimeError::UnimplementedInterruptFunc; }
+                }
+            }
+        }
+    })
+}
+
+fn is_vm(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => match &*reference.elem {
+            Type::Path(path) => path.path.segments.last().is_some_and(|s| s.ident == "VirtMach"),
+            _ => false
+        },
+        _ => false
+    }
+}
+
+/// The `T` of `Result<T, RuntimeError>`, `None` for other types.
+fn result_ok(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None; };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" { return None; }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None; };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None
+    }
+}
+
+fn count_returns(ty: &Type) -> usize {
+    if let Some(ok) = result_ok(ty) { return count_returns(ok); }
+    match ty {
+        Type::Tuple(tuple) => tuple.elems.len(),
+        _ => 1
+    }
+}
+
+fn push_returns(ty: &Type, value: TokenStream2) -> TokenStream2 {
+    if let Some(ok) = result_ok(ty) {
+        let push = push_returns(ok, quote!(__ok));
+        return quote!(match #value {
+            Ok(__ok) => { #push }
+            Err(err) => { vm.error = err; }
+        });
+    }
+    match ty {
+        Type::Tuple(tuple) => {
+            let pushes = (0..tuple.elems.len()).map(syn::Index::from).map(|i| quote!(vm.stack_push(#value.#i);));
+            quote!(#(#pushes)*)
+        }
+        _ => quote!(vm.stack_push(#value);)
+    }
+}
@@ -1,81 +1,129 @@
+use core::fmt::{Debug, Display};
+use core::num::ParseIntError;
+use core::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+use core::str::FromStr;
 use cfg_block::cfg_block;
 
 cfg_block! {
 
     #[cfg(feature = "i8")] {
-        pub const ATOM_ID: u8 = 1;
-        
         pub use i8 as VMAtom;
-        pub use i16 as VMAddr;        
-
-        pub trait VAtom { fn get_atom(&mut self) -> VMAtom; }
-
-        pub trait VAtomMut { fn put_atom(&mut self, _a:VMAtom); }
-
-        cfg_block! {
-            if #[cfg(feature="std")] {
-                use bytes::{Buf, BufMut};
-                
-                impl<T> VAtom for T where T: Buf { fn get_atom(&mut self) -> VMAtom { return self.get_i8(); } }
-                
-                impl<T> VAtomMut for T where T: BufMut { fn put_atom(&mut self, a:VMAtom) { self.put_i8(a); } }        
-            } else {
-                impl VAtom for &[u8] { fn get_atom(&mut self) -> VMAtom { return if self.len() < size_of::<VMAtom>() { VMAtom::MIN } else { let v = (self[0] as u8); if v >= 0x80 { -((0xff - v) as VMAtom) - 1 } else { v as VMAtom } } } }
-
-                impl VAtomMut for &[u8] { fn put_atom(&mut self, a:VMAtom) { unimplemented!("writing atoms not implement for no_std"); } }
-            }
-        }
     }
 
     #[cfg(any(feature = "i16", all(not(feature = "i8"), not(feature = "i32"))))] {
-        pub const ATOM_ID: u8 = 2;
-
         pub use i16 as VMAtom;
-        pub use i16 as VMAddr;
-
-        pub trait VAtom { fn get_atom(&mut self) -> VMAtom; }
+    }
 
-        pub trait VAtomMut { fn put_atom(&mut self, _a:VMAtom); }
+    #[cfg(feature = "i32")] {
+        pub use i32 as VMAtom;
+    }
+}
 
-        cfg_block! {
-            if #[cfg(feature="std")] {
-                use bytes::{Buf, BufMut};
-                
-                impl<T> VAtom for T where T: Buf { fn get_atom(&mut self) -> VMAtom { return self.get_i16_ne(); } }
-                
-                impl<T> VAtomMut for T where T: BufMut { fn put_atom(&mut self, a:VMAtom) { self.put_i16_ne(a); } }        
-            } else {
-                impl VAtom for &[u8] { fn get_atom(&mut self) -> VMAtom { return if self.len() < size_of::<VMAtom>() { VMAtom::MIN } else { let v = (self[0] as u16) + ((self[1] as u16) << 8); if v >= 0x8000 { -((0xffff - v) as VMAtom) - 1 } else { v as VMAtom } } } }
+/// The value type of registers, memory cells and immediates of a `VirtMach`.
+///
+/// `VMAtom`, selected by the `i8`/`i16`/`i32` features, only picks the default; VMs and
+/// programs of every atom type can be used side by side.
+///
+/// Atoms wrap around like 8-bit hardware: `add`/`sub` set carry on the unsigned carry or
+/// borrow, overflow on the signed overflow and sign on the top bit of the result, whether the
+/// atom is signed or not. Jump offsets are read as two's complement.
+pub trait Atom: Copy + Default + PartialEq + PartialOrd + Debug + Display + FromStr<Err = ParseIntError>
+    + Not<Output = Self> + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self>
+    + Shl<u32, Output = Self> + Shr<u32, Output = Self>
+{
+    /// Identifies the atom type in the header of a compiled binary: the size in bytes,
+    /// with bit 7 set for unsigned atoms.
+    const ATOM_ID: u8;
+    const SIZE: usize;
+    const BITS: u32;
+    const SIGNED: bool;
+    const MIN: Self;
+    const MAX: Self;
+    const ZERO: Self;
+
+    /// Atoms are stored little endian in bytecode, regardless of the host.
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+    fn to_le_bytes(self, bytes: &mut [u8]);
+
+    /// Converts with `as` semantics, wrapping values that do not fit.
+    fn from_i64(v: i64) -> Self;
+    fn to_i64(self) -> i64;
+    fn from_usize(v: usize) -> Option<Self>;
+    fn to_usize(self) -> Option<usize>;
+    /// Reads the atom as a two's complement offset, as used by jumps and calls.
+    fn to_offset(self) -> i64;
+    /// The top bit, reported as the sign flag.
+    fn sign_bit(self) -> bool;
+
+    /// Adds, returning the result, the unsigned carry and the signed overflow.
+    fn add_with_flags(self, b: Self) -> (Self, bool, bool);
+    /// Subtracts, returning the result, the unsigned borrow and the signed overflow.
+    fn sub_with_flags(self, b: Self) -> (Self, bool, bool);
+
+    fn overflowing_add(self, b: Self) -> (Self, bool);
+    fn overflowing_sub(self, b: Self) -> (Self, bool);
+    fn overflowing_mul(self, b: Self) -> (Self, bool);
+    fn overflowing_div(self, b: Self) -> (Self, bool);
+    fn overflowing_rem(self, b: Self) -> (Self, bool);
+    fn overflowing_pow(self, exp: u32) -> (Self, bool);
+    fn wrapping_neg(self) -> Self;
+}
 
-                impl VAtomMut for &[u8] { fn put_atom(&mut self, a:VMAtom) { unimplemented!("writing atoms not implement for no_std"); } }
-            }
+macro_rules! impl_atom {
+    ($t:ty, $s:ty, $u:ty, $id:expr) => {
+        impl Atom for $t {
+            const ATOM_ID: u8 = $id;
+            const SIZE: usize = size_of::<$t>();
+            const BITS: u32 = <$t>::BITS;
+            const SIGNED: bool = <$t>::MIN != 0;
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+            const ZERO: Self = 0;
+
+            fn from_le_bytes(bytes: &[u8]) -> Self { let mut buf = [0u8;size_of::<$t>()]; buf.copy_from_slice(&bytes[..size_of::<$t>()]); <$t>::from_le_bytes(buf) }
+            fn to_le_bytes(self, bytes: &mut [u8]) { bytes[..size_of::<$t>()].copy_from_slice(&<$t>::to_le_bytes(self)); }
+
+            fn from_i64(v: i64) -> Self { v as $t }
+            fn to_i64(self) -> i64 { self as i64 }
+            fn from_usize(v: usize) -> Option<Self> { <$t>::try_from(v).ok() }
+            fn to_usize(self) -> Option<usize> { usize::try_from(self).ok() }
+            fn to_offset(self) -> i64 { self as $s as i64 }
+            fn sign_bit(self) -> bool { (self as $s) < 0 }
+
+            fn add_with_flags(self, b: Self) -> (Self, bool, bool) { let (res, carry) = (self as $u).overflowing_add(b as $u); (res as $t, carry, (self as $s).overflowing_add(b as $s).1) }
+            fn sub_with_flags(self, b: Self) -> (Self, bool, bool) { let (res, borrow) = (self as $u).overflowing_sub(b as $u); (res as $t, borrow, (self as $s).overflowing_sub(b as $s).1) }
+
+            fn overflowing_add(self, b: Self) -> (Self, bool) { <$t>::overflowing_add(self, b) }
+            fn overflowing_sub(self, b: Self) -> (Self, bool) { <$t>::overflowing_sub(self, b) }
+            fn overflowing_mul(self, b: Self) -> (Self, bool) { <$t>::overflowing_mul(self, b) }
+            fn overflowing_div(self, b: Self) -> (Self, bool) { <$t>::overflowing_div(self, b) }
+            fn overflowing_rem(self, b: Self) -> (Self, bool) { <$t>::overflowing_rem(self, b) }
+            fn overflowing_pow(self, exp: u32) -> (Self, bool) { <$t>::overflowing_pow(self, exp) }
+            fn wrapping_neg(self) -> Self { <$t>::wrapping_neg(self) }
         }
-    }
+    };
+}
 
-    #[cfg(feature = "i32")] {
-        pub const ATOM_ID: u8 = 4;
-        
-        pub use i32 as VMAtom;
-        pub use i32 as VMAddr;
+impl_atom!(i8, i8, u8, 0x01);
+impl_atom!(i16, i16, u16, 0x02);
+impl_atom!(i32, i32, u32, 0x04);
+impl_atom!(i64, i64, u64, 0x08);
+impl_atom!(u8, i8, u8, 0x81);
+impl_atom!(u16, i16, u16, 0x82);
+impl_atom!(u32, i32, u32, 0x84);
 
-        pub trait VAtom { fn get_atom(&mut self) -> VMAtom; }
+pub trait VAtom { fn get_atom<A: Atom>(&mut self) -> A; }
 
-        pub trait VAtomMut { fn put_atom(&mut self, _a:VMAtom); }
+pub trait VAtomMut { fn put_atom<A: Atom>(&mut self, a: A); }
 
-        cfg_block! {
-            if #[cfg(feature="std")] {
-                use bytes::{Buf, BufMut};
-                
-                impl<T> VAtom for T where T: Buf { fn get_atom(&mut self) -> VMAtom { return self.get_i32_ne(); } }
-                
-                impl<T> VAtomMut for T where T: BufMut { fn put_atom(&mut self, a:VMAtom) { self.put_i32_ne(a); } }        
-            } else {
-                impl VAtom for &[u8] { fn get_atom(&mut self) -> VMAtom { return if self.len() < size_of::<VMAtom>() { VMAtom::MIN } else { let v = (self[0] as u32) + ((self[1] as u32) << 8) + ((self[1] as u32) << 16) + ((self[1] as u32) << 24); if v >= 0x80000000 { -((0xffffffff - v) as VMAtom) - 1 } else { v as VMAtom } } } }
+impl VAtom for &[u8] { fn get_atom<A: Atom>(&mut self) -> A { if self.len() < A::SIZE { return A::MIN; } let a = A::from_le_bytes(self); *self = &self[A::SIZE..]; a } }
 
-                impl VAtomMut for &[u8] { fn put_atom(&mut self, a:VMAtom) { unimplemented!("writing atoms not implement for no_std"); } }
-            }
-        }
+cfg_block! {
+    if #[cfg(feature="std")] {
+        use bytes::BufMut;
+
+        impl<T> VAtomMut for T where T: BufMut { fn put_atom<A: Atom>(&mut self, a: A) { let mut buf = [0u8;8]; a.to_le_bytes(&mut buf); self.put_slice(&buf[..A::SIZE]); } }
+    } else {
+        impl VAtomMut for &mut [u8] { fn put_atom<A: Atom>(&mut self, a: A) { a.to_le_bytes(self); let rest = core::mem::take(self); *self = &mut rest[A::SIZE..]; } }
     }
 }
-
-
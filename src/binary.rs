@@ -0,0 +1,163 @@
+//! Container format of compiled binaries.
+//!
+//! ```text
+//! offset size
+//!      0    4  magic "VMBC"
+//!      4    1  format version
+//!      5    1  byte order of atoms in the code, always 0 (little endian)
+//!      6    1  atom type (`Atom::ATOM_ID`)
+//!      7    1  register count the code was compiled for
+//!      8    4  code length
+//!     12    4  entry point, offset into the code
+//!     16    4  CRC-32 of the header's first 16 bytes followed by code and import table
+//!     20       code
+//!              import table: count (1), then per interrupt its name length (1) and name
+//! ```
+//!
+//! All header fields are little endian. The `n`th entry of the import table names the
+//! interrupt that `int n` calls; the VM binds it against `SoftInterrupt::name()`.
+
+use crate::{Atom, RuntimeError};
+
+pub const MAGIC: [u8;4] = *b"VMBC";
+pub const FORMAT_VERSION: u8 = 3;
+pub const HEADER_SIZE: usize = 20;
+/// Interrupt slots addressable by `int`.
+pub const IMPORT_MAX: usize = 15;
+
+pub const LITTLE_ENDIAN: u8 = 0;
+pub const BIG_ENDIAN: u8 = 1;
+/// Byte order of immediates and jump offsets, independent of the host.
+pub const ATOM_ENDIAN: u8 = LITTLE_ENDIAN;
+
+const CHECKSUM_OFFSET: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub endianness: u8,
+    pub atom_id: u8,
+    /// Registers the code was compiled for, a VM needs at least as many to run it.
+    pub regs: u8,
+    pub code_len: usize,
+    pub entry: usize,
+    pub checksum: u32
+}
+
+impl Header {
+    /// Reads the header fields without validating them, `None` if `data` is too short or
+    /// does not start with `MAGIC`.
+    pub fn parse(data: &[u8]) -> Option<Header> {
+        if data.len() < HEADER_SIZE || data[0..4] != MAGIC { return None; }
+        Some(Header {
+            version: data[4],
+            endianness: data[5],
+            atom_id: data[6],
+            regs: data[7],
+            code_len: read_u32(&data[8..]) as usize,
+            entry: read_u32(&data[12..]) as usize,
+            checksum: read_u32(&data[16..])
+        })
+    }
+
+    /// Builds the header for `code` and its import `table`, compiled for atom type `A` and
+    /// `regs` registers.
+    pub fn for_code<A: Atom>(code: &[u8], table: &[u8], entry: usize, regs: usize) -> Header {
+        let mut header = Header { version: FORMAT_VERSION, endianness: ATOM_ENDIAN, atom_id: A::ATOM_ID, regs: regs as u8, code_len: code.len(), entry, checksum: 0 };
+        let mut buf = [0u8;HEADER_SIZE];
+        header.write(&mut buf);
+        header.checksum = !crc32(crc32(crc32(!0, &buf[..CHECKSUM_OFFSET]), code), table);
+        header
+    }
+
+    pub fn write(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&MAGIC);
+        buf[4] = self.version;
+        buf[5] = self.endianness;
+        buf[6] = self.atom_id;
+        buf[7] = self.regs;
+        buf[8..12].copy_from_slice(&(self.code_len as u32).to_le_bytes());
+        buf[12..16].copy_from_slice(&(self.entry as u32).to_le_bytes());
+        buf[16..20].copy_from_slice(&self.checksum.to_le_bytes());
+    }
+}
+
+/// Checks that `data` is a complete, intact binary for atom type `A` and returns its header.
+pub fn validate<A: Atom>(data: &[u8]) -> Result<Header, RuntimeError> {
+    let header = Header::parse(data).ok_or(RuntimeError::MalformedHeader)?;
+    if header.version != FORMAT_VERSION { return Err(RuntimeError::UnsupportedVersion); }
+    if header.endianness != ATOM_ENDIAN { return Err(RuntimeError::MismatchedEndianness); }
+    if header.atom_id != A::ATOM_ID { return Err(RuntimeError::MismatchedAtomType); }
+    let code_end = HEADER_SIZE.checked_add(header.code_len).filter(|end| *end <= data.len()).ok_or(RuntimeError::TruncatedProgram)?;
+    if checksum(data) != header.checksum { return Err(RuntimeError::ChecksumMismatch); }
+    if !Imports::well_formed(&data[code_end..]) { return Err(RuntimeError::MalformedImportTable); }
+    if header.code_len > 0 && header.entry >= header.code_len { return Err(RuntimeError::InstructionPointerOutOfBounds); }
+    Ok(header)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    !crc32(crc32(!0, &data[..CHECKSUM_OFFSET]), &data[HEADER_SIZE..])
+}
+
+/// The interrupt names of a binary's import table, in slot order.
+#[derive(Clone)]
+pub struct Imports <'a> {
+    table: &'a [u8],
+    remaining: usize
+}
+
+impl <'a> Imports <'a> {
+    /// Reads the import table following the code of `data`, empty if there is none.
+    pub fn of(data: &'a [u8]) -> Imports<'a> {
+        let table = Header::parse(data).and_then(|header| HEADER_SIZE.checked_add(header.code_len)).and_then(|end| data.get(end..)).unwrap_or(&[]);
+        Imports::table(table)
+    }
+
+    /// Reads an import table that starts at the beginning of `table`.
+    pub(crate) fn table(table: &'a [u8]) -> Imports<'a> {
+        match table.split_first() {
+            Some((count, table)) => Imports { table, remaining: *count as usize },
+            None => Imports { table, remaining: 0 }
+        }
+    }
+
+    fn well_formed(table: &[u8]) -> bool {
+        let Some((count, mut rest)) = table.split_first() else { return false; };
+        if *count as usize > IMPORT_MAX { return false; }
+        for _ in 0 .. *count {
+            let Some((len, tail)) = rest.split_first() else { return false; };
+            let Some(name) = tail.get(..*len as usize) else { return false; };
+            if name.is_empty() || core::str::from_utf8(name).is_err() { return false; }
+            rest = &tail[name.len()..];
+        }
+        rest.is_empty()
+    }
+}
+
+impl <'a> Iterator for Imports <'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.remaining == 0 { return None; }
+        let (len, tail) = self.table.split_first()?;
+        let name = tail.get(..*len as usize)?;
+        self.table = &tail[name.len()..];
+        self.remaining -= 1;
+        core::str::from_utf8(name).ok()
+    }
+}
+
+/// CRC-32 (IEEE 802.3) without lookup table, to keep the footprint small.
+fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0 .. 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
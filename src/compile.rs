@@ -1,12 +1,9 @@
-extern crate alloc;
 extern crate std;
 
-use alloc::alloc::{alloc_zeroed, Layout};
 use bytes::{BufMut, BytesMut};
-use std::{collections::HashMap, vec::Vec, slice, string::String, format };
-use csv;
+use std::{collections::HashMap, vec, vec::Vec, string::String, format };
 
-use crate::{ATOM_ID, Program, VAtomMut, VMAtom, VirtMach, opcodes::OpCode, interrupts::BASE_INTERRUPT_MAPS};
+use crate::{Atom, ProgramBuf, VAtomMut, binary::{Header, HEADER_SIZE, IMPORT_MAX}, VMAtom, VirtMach, opcodes::{OpCode, ExtOpCode, Cond}, interrupts::{SoftInterruptDef, BASE_INTERRUPT_DEFS}};
 
 #[derive(Debug)]
 pub enum ListingError <'a> {
@@ -18,6 +15,7 @@ pub enum ListingError <'a> {
     MalformedDefine(usize, &'a str),    
     IllegalDefineValue(usize, &'a str),    
     UnknownLabel(usize, &'a str),    
+    LabelOutOfRange(usize, &'a str),
     UnknownInterrupt(usize, &'a str),
     UnknownFunction(usize, &'a str),
     MalformedFunction(usize, &'a str)    
@@ -33,36 +31,46 @@ struct Label <'a> {
 struct Jump <'a> {
     label: &'a str,
     address: usize,
-    line_no: usize
+    line_no: usize,
+    /// Jumps and calls can be relaxed into the far encoding, `ivt` offsets cannot.
+    relax: bool,
+    far: bool,
+    /// Calls push the address behind them, which has to fit an atom.
+    call: bool,
+    size: usize
 }
 
 #[derive(Debug, PartialEq)]
-pub enum Argument <'a> {
+pub enum Argument <'a, A: Atom = VMAtom> {
     Empty(),
     Error(&'a str),    
     Ignore(),
     Register(u8),
-    Atom(VMAtom),
-    Label(&'a str)
+    Atom(A),
+    Label(&'a str),
+    /// `[rN+#k]` or `[rN]`
+    Indexed(u8, A),
+    /// `[rN]+`
+    PostIncrement(u8)
 }
 
-impl VirtMach <'_> {
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
     
-    fn parse_argument <'a> (mut arg: &'a str, defines: &HashMap::<&'a str, &'a str>) -> Argument<'a> {
+    fn parse_argument <'a> (mut arg: &'a str, defines: &HashMap::<&'a str, &'a str>) -> Argument<'a, A> {
         defines.get(arg).inspect(|value|{ arg = value; });
         if arg.is_empty() { Argument::Empty() } else
-        if arg == "_" { Argument::Ignore() } else { match &arg[0..1] {
+        if arg == "_" { Argument::Ignore() } else if arg.starts_with("[") { Self::parse_address(arg, defines) } else { match &arg[0..1] {
             "r" => match arg[1..].parse::<u8>() {
                 Ok(r) => match r { 
-                    0 .. 14 => Argument::Register(r),
+                    0 ..= 14 => Argument::Register(r),
                     _ => Argument::Error("malformed register") 
                 }
                 _ => Argument::Label(arg)
             }                                                  
             "#" => match &arg[1..] {
-                "min" => Argument::Atom(VMAtom::MIN),
-                "max" => Argument::Atom(VMAtom::MAX),
-                _ => match arg[1..].parse::<VMAtom>() {
+                "min" => Argument::Atom(A::MIN),
+                "max" => Argument::Atom(A::MAX),
+                _ => match arg[1..].parse::<A>() {
                     Ok(a) => Argument::Atom(a),
                     _ => Argument::Error("malformed value")                                        
                 }
@@ -71,57 +79,68 @@ impl VirtMach <'_> {
         } }
     }
 
-    fn parse_function_map(external_interrupts: Vec::<(String, String)>) -> HashMap::<String, (u8, VMAtom, usize, usize)> {
-        let mut map: HashMap::<String, (u8, VMAtom, usize, usize)> = HashMap::new();
-
-        fn add(map: &mut HashMap::<String, (u8, VMAtom, usize, usize)>, int_no: u8, int_name: &str, int_csv: &str) {
-            map.insert(String::from(int_name), (int_no as u8, 0, 0, 0));
-            
-            let mut rdr = csv::ReaderBuilder::new()
-                .has_headers(false)
-                .from_reader(int_csv.as_bytes());
-            for result in rdr.records() {            
-                if result.is_ok() {
-                    let record = result.unwrap();                
-                    if record.len() == 5 {
-                        let func_no = record[0].trim().parse::<VMAtom>().unwrap();
-                        let func_name = &record[1];
-                        let arg_cnt = record[2].trim().parse::<usize>().unwrap();
-                        let res_cnt = record[3].trim().parse::<usize>().unwrap();
-                        
-                        let func = (int_no as u8, func_no, arg_cnt, res_cnt);                    
-                        map.insert(format!("{}.{}", int_name, func_name.trim()), func);
-                    }        
-                }
+    fn parse_address <'a> (arg: &'a str, defines: &HashMap::<&'a str, &'a str>) -> Argument<'a, A> {
+        let (inner, post_increment) = match arg.strip_suffix("]+") {
+            Some(inner) => (&inner[1..], true),
+            None => match arg.strip_suffix("]") { Some(inner) => (&inner[1..], false), None => return Argument::Error("malformed address") }
+        };
+        let (base, offset) = match inner.split_once("+") {
+            Some((base, offset)) if !post_increment => (base.trim(), Some(offset.trim())),
+            Some(_) => return Argument::Error("malformed address"),
+            None => (inner.trim(), None)
+        };
+        let Argument::Register(reg) = Self::parse_argument(base, defines) else { return Argument::Error("address needs a base register"); };
+        match offset {
+            Some(offset) => match Self::parse_argument(offset, defines) {
+                Argument::Atom(a) => Argument::Indexed(reg, a),
+                _ => Argument::Error("malformed address offset")
             }
+            None if post_increment => Argument::PostIncrement(reg),
+            None => Argument::Indexed(reg, A::ZERO)
         }
-        
-        for (int_no, (int_name, int_csv)) in BASE_INTERRUPT_MAPS.iter().enumerate() {
-            add(&mut map, int_no as u8, *int_name, *int_csv);
-        }
+    }
 
-        for (ext_no, (int_name, int_csv)) in external_interrupts.iter().enumerate() {
-            add(&mut map, (ext_no + BASE_INTERRUPT_MAPS.len()) as u8, int_name.as_str(), int_csv.as_str());
-        }    
+    fn parse_function_map(interrupts: &[SoftInterruptDef]) -> HashMap::<String, (String, A, usize, usize)> {
+        let mut map: HashMap::<String, (String, A, usize, usize)> = HashMap::new();
+
+        for def in BASE_INTERRUPT_DEFS.iter().chain(interrupts) {
+            map.insert(String::from(def.name), (String::from(def.name), A::ZERO, 0, 0));
+            for func in def.functions {
+                // functions whose number does not fit the atom cannot be called
+                let func_no = A::from_i64(func.no as i64);
+                if func_no.to_i64() != func.no as i64 { continue; }
+                map.insert(format!("{}.{}", def.name, func.name), (String::from(def.name), func_no, func.arguments, func.returns));
+            }
+        }
     
         return map;
     }
+
+    /// Returns the import table slot of interrupt `name`, appending it on first use.
+    fn import_slot(imports: &mut Vec<String>, name: &str) -> Option<u8> {
+        match imports.iter().position(|a| a == name) {
+            Some(slot) => Some(slot as u8),
+            None if imports.len() < IMPORT_MAX && name.len() <= u8::MAX as usize => { imports.push(String::from(name)); Some((imports.len() - 1) as u8) }
+            None => None
+        }
+    }
     
-    pub fn compile <'a> (name: &'a str, listing: &'a str, function_definitions: Vec::<(String, String)>) -> Result<( Program<'a>, *const u8 ), ListingError<'a>> {        
-        let functions = VirtMach::parse_function_map(function_definitions);        
+    pub fn compile <'a> (name: &'a str, listing: &'a str, interrupts: &[SoftInterruptDef]) -> Result<ProgramBuf<A>, ListingError<'a>> {        
+        let functions = Self::parse_function_map(interrupts);        
         
         let mut dest = BytesMut::new(); 
-        
-        dest.put_u8(ATOM_ID);               
 
         let mut len = 0;
 
         let mut labels = [Label { name: "", address: 0 };128];
         let mut labels_i = 0usize;
-        let mut jumps = [Jump { label: "", address: 0, line_no: 0 };128];
+        let mut jumps = [Jump { label: "", address: 0, line_no: 0, relax: false, far: false, call: false, size: 0 };128];
         let mut jumps_i = 0usize;
                   
         let mut defines = HashMap::<&str, &str>::new();         
+        let mut entry: Option<(usize, &str)> = None;
+        let mut imports = Vec::<String>::new();
+        let mut int_slots: Option<(usize, u8)> = None;
                 
         for (i, mut line) in listing.lines().enumerate() {   
             let line_no = i + 1;
@@ -144,10 +163,18 @@ impl VirtMach <'_> {
                                 return Err(ListingError::MalformedDefine(line_no, "malformed def"));
                             }  
                         }
+                        "ent" => {
+                            if def.len() == 2 {
+                                entry = Some((line_no, def[1].trim()));
+                            }else{
+                                return Err(ListingError::MalformedDefine(line_no, "malformed ent"));
+                            }  
+                        }
                         "req" => {
                             if def.len() == 2 {
                                 let int_name = def[1].trim();                                 
                                 if !functions.contains_key(int_name) { return Err(ListingError::MalformedDefine(line_no, "required interrupt not found")); }
+                                if Self::import_slot(&mut imports, int_name).is_none() { return Err(ListingError::IllegalInterrupt(line_no, "too many interrupts")); }
                             }else{
                                 return Err(ListingError::MalformedDefine(line_no, "malformed req"));
                             }  
@@ -181,37 +208,42 @@ impl VirtMach <'_> {
                 let mut outputs_str = None;
                 head.next().inspect(|a| { outputs_str = Some(function_str); function_str = a.trim();  });
                                 
-                let mut outputs: Vec<Argument> = if outputs_str.is_some() { outputs_str.unwrap().split(",").map(|a| VirtMach::parse_argument(a.trim(), &defines)).collect() } else { Vec::new() };
-                let mut inputs: Vec<Argument> = inputs_str.split(",").map(|a| VirtMach::parse_argument(a.trim(), &defines)).collect();
+                let mut outputs: Vec<Argument<A>> = if outputs_str.is_some() { outputs_str.unwrap().split(",").map(|a| Self::parse_argument(a.trim(), &defines)).collect() } else { Vec::new() };
+                let mut inputs: Vec<Argument<A>> = inputs_str.split(",").map(|a| Self::parse_argument(a.trim(), &defines)).collect();
                 if inputs.len() == 1 { match inputs[0] { Argument::Empty() => { inputs.clear(); }, _ => {} } }
 
                 let ignore_inputs = inputs.len() == 1 && inputs[0] == Argument::Ignore();
                 let ignore_outputs = outputs.len() == 1 && outputs[0] == Argument::Ignore();
                                 
-                let mut function: Option<(u8, VMAtom)> = None;
+                let mut function: Option<(u8, A)> = None;
                 if functions.contains_key(function_str) {
                     let func = functions.get(function_str).unwrap();
                     if !ignore_inputs && func.2 != inputs.len() { std::println!("{:?}", inputs); return Err(ListingError::MalformedFunction(line_no, "wrong number of arguments")); }
                     if !ignore_outputs && func.3 != outputs.len() { return Err(ListingError::MalformedFunction(line_no, "wrong number of return values")); }
-                    function = Some((func.0, func.1));
+                    let Some(slot) = Self::import_slot(&mut imports, &func.0) else { return Err(ListingError::IllegalInterrupt(line_no, "too many interrupts")); };
+                    function = Some((slot, func.1));
                 }                
 
                 if function.is_none() { return Err(ListingError::UnknownFunction(line_no, function_str)); }
                 if !ignore_outputs {
                     for output in &outputs { match output {
+                        Argument::Register(reg) if *reg as usize >= REGS => { return Err(ListingError::IllegalRegister(line_no, "register not available on target")); }
                         Argument::Empty() => { return Err(ListingError::MalformedFunction(line_no, "empty output argument")); },
                         Argument::Error(_) => { return Err(ListingError::MalformedFunction(line_no, "malformed output argument")); }
                         Argument::Label(_) => { return Err(ListingError::MalformedFunction(line_no, "did not expect a label as output")); }
                         Argument::Ignore() => { return Err(ListingError::MalformedFunction(line_no, "cannot ignore a single output")); }
+                        Argument::Indexed(..) | Argument::PostIncrement(_) => { return Err(ListingError::MalformedFunction(line_no, "did not expect an address as output")); }
                         _ => {}
                     } }
                 }
                 if !ignore_inputs {
                     for input in &inputs { match input {
+                        Argument::Register(reg) if *reg as usize >= REGS => { return Err(ListingError::IllegalRegister(line_no, "register not available on target")); }
                         Argument::Empty() => { return Err(ListingError::MalformedFunction(line_no, "empty input argument")); },
                         Argument::Error(_) => { return Err(ListingError::MalformedFunction(line_no, "malformed input argument")); }
                         Argument::Label(_) => { return Err(ListingError::MalformedFunction(line_no, "did not expect a label as input")); }
                         Argument::Ignore() => { return Err(ListingError::MalformedFunction(line_no, "cannot ignore a single input")); }
+                        Argument::Indexed(..) | Argument::PostIncrement(_) => { return Err(ListingError::MalformedFunction(line_no, "did not expect an address as input")); }
                         _ => {}
                     } }
                 }
@@ -227,7 +259,7 @@ impl VirtMach <'_> {
                                 Argument::Atom(val) => {
                                     dest.put_u8(OpCode::PSH as u8 | (0x0f << 4));
                                     dest.put_atom(*val);
-                                    len += 1 + size_of::<VMAtom>();
+                                    len += 1 + A::SIZE;
                                 }
                                 _ => {}
                             }            
@@ -235,8 +267,8 @@ impl VirtMach <'_> {
                 }
 
                 dest.put_u8(OpCode::PSH as u8 | (0x0f << 4));
-                dest.put_atom(function.unwrap().1 as VMAtom);
-                len += 1 + size_of::<VMAtom>();
+                dest.put_atom(function.unwrap().1);
+                len += 1 + A::SIZE;
                 dest.put_u8(OpCode::INT as u8 | (function.unwrap().0 << 4));
                 len += 1;
 
@@ -251,7 +283,7 @@ impl VirtMach <'_> {
                                 Argument::Atom(val) => {
                                     dest.put_u8(OpCode::POP as u8 | (0x0f << 4));
                                     dest.put_atom(*val);
-                                    len += 1 + size_of::<VMAtom>();
+                                    len += 1 + A::SIZE;
                                 }
                                 _ => {}
                             }            
@@ -267,10 +299,13 @@ impl VirtMach <'_> {
                     arg = next.unwrap_or("").trim();
                 }
                 
-                let argument = VirtMach::parse_argument(arg, &defines);
+                let mut argument = Self::parse_argument(arg, &defines);
 
                 let mut args: u8 = 0b011;
-                let mut range = VMAtom::MIN..VMAtom::MAX;
+                let mut range = A::MIN..=A::MAX;
+                let mut ext: Option<ExtOpCode> = None;
+                // high nibble of the `EXT` byte, the `Cond` of a jump or the `djn` counter register
+                let mut nibble = 0u8;
                 let op_res = match op.to_ascii_lowercase().as_str() {                    
                     "reg" => { args = 0b001; OpCode::REG }
                     "set" => { OpCode::SET }
@@ -281,7 +316,7 @@ impl VirtMach <'_> {
                     "add" => { OpCode::ADD }                                        
                     "sub" => { OpCode::SUB }                    
                     "cal" => { args = 0b110; OpCode::CAL }                                        
-                    "int" => { args = 0b110; range = 0..15; OpCode::INT }                                        
+                    "int" => { args = 0b110; range = A::ZERO..=A::from_i64(14); OpCode::INT }                                        
                     "jmp" => { args = 0b110; OpCode::JMP }                                        
                     "jpz" => { args = 0b110; OpCode::JPZ }                                                            
                     "jpc" => { args = 0b110; OpCode::JPC }                                        
@@ -290,6 +325,30 @@ impl VirtMach <'_> {
                     "clr" => { OpCode::CLR }                    
                     "inv" => { OpCode::INV }                    
                     "neg" => { OpCode::NEG }                    
+                    "cmp" => { ext = Some(ExtOpCode::CMP); OpCode::EXT }
+                    "and" => { ext = Some(ExtOpCode::AND); OpCode::EXT }
+                    "or" => { ext = Some(ExtOpCode::OR); OpCode::EXT }
+                    "xor" => { ext = Some(ExtOpCode::XOR); OpCode::EXT }
+                    "shl" => { ext = Some(ExtOpCode::SHL); OpCode::EXT }
+                    "shr" => { ext = Some(ExtOpCode::SHR); OpCode::EXT }
+                    "mul" => { ext = Some(ExtOpCode::MUL); OpCode::EXT }
+                    "inc" => { args = 0b000; ext = Some(ExtOpCode::INC); OpCode::EXT }
+                    "dec" => { args = 0b000; ext = Some(ExtOpCode::DEC); OpCode::EXT }
+                    "swap" => { args = 0b001; ext = Some(ExtOpCode::SWP); OpCode::EXT }
+                    "jlt" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::LT as u8; OpCode::EXT }
+                    "jle" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::LE as u8; OpCode::EXT }
+                    "jgt" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::GT as u8; OpCode::EXT }
+                    "jge" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::GE as u8; OpCode::EXT }
+                    "jlo" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::LO as u8; OpCode::EXT }
+                    "jls" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::LS as u8; OpCode::EXT }
+                    "jhi" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::HI as u8; OpCode::EXT }
+                    "jhs" => { args = 0b100; ext = Some(ExtOpCode::JCC); nibble = Cond::HS as u8; OpCode::EXT }
+                    "djn" => { args = 0b100; ext = Some(ExtOpCode::DJN); OpCode::EXT }
+                    "ei" => { OpCode::EI }
+                    "di" => { OpCode::DI }
+                    "rti" => { OpCode::RTI }
+                    "ivt" => { OpCode::IVT }
+                    "wfi" => { OpCode::WFI }
                     "brk" => { OpCode::BRK }
                     "hlt" => { OpCode::HLT }
                     "end" => { OpCode::END }                    
@@ -298,13 +357,23 @@ impl VirtMach <'_> {
                 }; 
                 let op_u8 = op_res as u8;
 
-                if op_u8 & 0x0f == 0x0f { args = 0b000; }
+                if op_u8 & 0x0f == 0x0f { args = if matches!(op_res, OpCode::IVT) { 0b100 } else { 0b000 }; }
+
+                if matches!(ext, Some(ExtOpCode::DJN)) {
+                    match argument {
+                        Argument::Register(reg) if (reg as usize) < REGS => { nibble = reg; }
+                        _ => return Err(ListingError::IllegalArgument(line_no, "expected a counter register"))
+                    }
+                    argument = Self::parse_argument(instruction.map(|a| a.trim()).find(|a| !a.is_empty()).unwrap_or(""), &defines);
+                }
 
                 match argument {
+                    Argument::Register(reg) if reg as usize >= REGS => return Err(ListingError::IllegalRegister(line_no, arg)),
                     Argument::Register(reg) => if args & 0b001 == 0 {
                         return Err(ListingError::IllegalArgument(line_no, "did not expect a register"))                        
                     } else {
                         dest.put_u8(op_u8 | (reg << 4));
+                        if let Some(ext) = ext { dest.put_u8(ext as u8); len += 1; }
                         len += 1;
                     },
                     Argument::Atom(num) => if args & 0b010 == 0 {
@@ -313,13 +382,16 @@ impl VirtMach <'_> {
                         if range.contains(&num) {
                             match op_res {
                                 OpCode::INT => {
-                                    dest.put_u8(op_u8 | (num << 4) as u8);                                    
+                                    let slot = num.to_i64() as u8;
+                                    if int_slots.is_none_or(|(_, max)| slot > max) { int_slots = Some((line_no, slot)); }
+                                    dest.put_u8(op_u8 | slot << 4);                                    
                                     len += 1;
                                 }
                                 _ => {
                                     dest.put_u8(op_u8 | 0xf0);
+                                    if let Some(ext) = ext { dest.put_u8(ext as u8); len += 1; }
                                     dest.put_atom(num);                                       
-                                    len += 1 + size_of::<VMAtom>();
+                                    len += 1 + A::SIZE;
                                 }
                             }
                         } else {
@@ -332,28 +404,63 @@ impl VirtMach <'_> {
                         match op_res {
                             OpCode::INT => {
                                 if functions.contains_key(label) {
-                                    dest.put_u8(op_u8 | (functions.get(label).unwrap().0 << 4) as u8);                                    
+                                    let Some(slot) = Self::import_slot(&mut imports, &functions.get(label).unwrap().0) else { return Err(ListingError::IllegalInterrupt(line_no, "too many interrupts")); };
+                                    dest.put_u8(op_u8 | slot << 4);                                    
                                     len += 1;
                                 }else{
                                     return Err(ListingError::UnknownInterrupt(line_no, label));
                                 }
                             }
                             _ => {
-                                dest.put_u8(op_u8 | 0xf0);
-                                dest.put_atom(0);
-                                len += 1 + size_of::<VMAtom>();
+                                let start = len;
+                                if let Some(ext) = ext {
+                                    dest.put_u8(op_u8 | nibble << 4);
+                                    dest.put_u8(ext as u8);
+                                    len += 1;
+                                } else {
+                                    dest.put_u8(if op_u8 & 0x0f == 0x0f { op_u8 } else { op_u8 | 0xf0 });
+                                }
+                                dest.put_atom(A::ZERO);
+                                len += 1 + A::SIZE;
                                 jumps[jumps_i].label = label;
                                 jumps[jumps_i].address = len;   
+                                jumps[jumps_i].line_no = line_no;
+                                jumps[jumps_i].relax = op_u8 & 0x0f != 0x0f && !matches!(ext, Some(ExtOpCode::DJN));
+                                jumps[jumps_i].call = matches!(op_res, OpCode::CAL);
+                                jumps[jumps_i].size = len - start;
                                 jumps_i += 1;     
                             }
                         }                        
                     },
                     Argument::Empty() => if args == 0b000 {
                         dest.put_u8(op_u8);
+                        if let Some(ext) = ext { dest.put_u8(ext as u8); len += 1; }
                         len += 1;
                     } else {
                         return Err(ListingError::IllegalArgument(line_no, "missing argument"))
                     },
+                    Argument::Indexed(reg, _) | Argument::PostIncrement(reg) if reg as usize >= REGS => return Err(ListingError::IllegalRegister(line_no, arg)),
+                    Argument::Indexed(reg, offset) => match op_res {
+                        OpCode::LOA | OpCode::STO if offset == A::ZERO => {
+                            dest.put_u8(op_u8 | (reg << 4));
+                            len += 1;
+                        }
+                        OpCode::LOA | OpCode::STO => {
+                            dest.put_u8(OpCode::EXT as u8 | (reg << 4));
+                            dest.put_u8(if matches!(op_res, OpCode::LOA) { ExtOpCode::LOX } else { ExtOpCode::STX } as u8);
+                            dest.put_atom(offset);
+                            len += 2 + A::SIZE;
+                        }
+                        _ => return Err(ListingError::IllegalArgument(line_no, "did not expect an address"))
+                    },
+                    Argument::PostIncrement(reg) => match op_res {
+                        OpCode::LOA | OpCode::STO => {
+                            dest.put_u8(OpCode::EXT as u8 | (reg << 4));
+                            dest.put_u8(if matches!(op_res, OpCode::LOA) { ExtOpCode::LOI } else { ExtOpCode::STI } as u8);
+                            len += 2;
+                        }
+                        _ => return Err(ListingError::IllegalArgument(line_no, "did not expect an address"))
+                    },
                     Argument::Ignore() => return Err(ListingError::IllegalArgument(line_no, "unexpected _ argument")),
                     Argument::Error(err) => return Err(ListingError::IllegalArgument(line_no, err))
                     
@@ -361,25 +468,73 @@ impl VirtMach <'_> {
             }            
         }      
         
-        for jump in jumps {
-            if jump.label.len() == 0 { break; }
-            for label in labels {
-                if label.name.len() == 0 { return Err(ListingError::UnknownLabel(jump.line_no, jump.label)); }
-                if label.name == jump.label {
-                    let diff = label.address as VMAtom - jump.address as VMAtom;                    
-                    dest[1 + jump.address - size_of::<VMAtom>()..].as_mut().put_atom(diff);
-                    break; 
-                }
+        // relax jumps whose offset does not fit the atom into the far encoding, which moves
+        // the code behind them and can push further jumps out of range
+        let far_size = 4;
+        loop {
+            let mut relaxed = false;
+            for j in 0 .. jumps_i {
+                let jump = jumps[j];
+                if jump.far { continue; }
+                let Some(label) = labels[..labels_i].iter().find(|l| l.name == jump.label) else { return Err(ListingError::UnknownLabel(jump.line_no, jump.label)); };
+                let diff = label.address as i64 - jump.address as i64;
+                if A::from_i64(diff).to_offset() == diff { continue; }
+                if !jump.relax || far_size < jump.size { return Err(ListingError::LabelOutOfRange(jump.line_no, jump.label)); }
+
+                // conditional jumps of the extended page keep their first byte
+                let start = jump.address - jump.size;
+                let op = if dest[start] & 0x0f == OpCode::EXT as u8 { dest[start] } else { (dest[start] & 0x0f) << 4 | OpCode::EXT as u8 };
+                let tail = dest.split_off(jump.address);
+                dest.truncate(start);
+                dest.put_u8(op);
+                dest.put_u8(ExtOpCode::FAR as u8);
+                dest.put_u16_le(0);
+                dest.extend_from_slice(&tail);
+
+                let grow = far_size - jump.size;
+                for label in labels[..labels_i].iter_mut() { if label.address > start { label.address += grow; } }
+                for jump in jumps[..jumps_i].iter_mut() { if jump.address > start { jump.address += grow; } }
+                jumps[j].far = true;
+                relaxed = true;
+            }
+            if !relaxed { break; }
+        }
+
+        for jump in &jumps[..jumps_i] {
+            let Some(label) = labels[..labels_i].iter().find(|l| l.name == jump.label) else { return Err(ListingError::UnknownLabel(jump.line_no, jump.label)); };
+            if jump.call && A::from_usize(jump.address).is_none() { return Err(ListingError::LabelOutOfRange(jump.line_no, jump.label)); }
+            if jump.far {
+                let Ok(target) = u16::try_from(label.address) else { return Err(ListingError::LabelOutOfRange(jump.line_no, jump.label)); };
+                dest[jump.address - 2 .. jump.address].copy_from_slice(&target.to_le_bytes());
+            } else {
+                let diff = A::from_i64(label.address as i64 - jump.address as i64);                    
+                dest[jump.address - A::SIZE..].as_mut().put_atom(diff);
             }
         }                        
 
-        let buf = unsafe { alloc_zeroed(Layout::from_size_align( dest.len(), 1).unwrap()) };        
-        unsafe { buf.copy_from(dest.as_ptr(), dest.len()); }
+        let entry = match entry {
+            Some((line_no, label)) => match labels.iter().find(|l| l.name == label) {
+                Some(l) if !l.name.is_empty() => l.address,
+                _ => return Err(ListingError::UnknownLabel(line_no, label))
+            }
+            None => 0
+        };
+
+        if let Some((line_no, slot)) = int_slots && slot as usize >= imports.len() {
+            return Err(ListingError::IllegalInterrupt(line_no, "no interrupt imported at this slot"));
+        }
+
+        let mut table = vec![imports.len() as u8];
+        for name in &imports {
+            table.push(name.len() as u8);
+            table.extend_from_slice(name.as_bytes());
+        }
+
+        let mut data = vec![0u8;HEADER_SIZE];
+        Header::for_code::<A>(&dest, &table, entry, REGS).write(&mut data);
+        data.extend_from_slice(&dest);
+        data.extend_from_slice(&table);
 
-        return Ok((Program {
-                source: 254,
-                id: name,
-                data: unsafe { slice::from_raw_parts(buf, dest.len()) }
-            }, buf ));
+        return Ok(ProgramBuf::new(254, String::from(name), data));
     }        
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use crate::{Atom, VirtMach};
+
+/// Number of host breakpoints a `VirtMach` can hold.
+pub const BREAKPOINT_MAX: usize = 8;
+/// Number of watchpoints a `VirtMach` can hold.
+pub const WATCHPOINT_MAX: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchTarget {
+    Memory(usize),
+    Register(usize)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchCondition<A: Atom> {
+    Read,
+    Write,
+    /// A write of the given value.
+    Value(A)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Watchpoint<A: Atom> {
+    pub target: WatchTarget,
+    pub condition: WatchCondition<A>
+}
+
+pub(crate) struct Debugger<A: Atom> {
+    breakpoints: [Option<usize>;BREAKPOINT_MAX],
+    watchpoints: [Option<Watchpoint<A>>;WATCHPOINT_MAX],
+    watching: bool,
+    /// Lets the instruction a breakpoint stopped at execute when resuming.
+    pub(crate) resume: bool,
+    pub(crate) hit: Option<usize>
+}
+
+impl <A: Atom> Debugger<A> {
+    pub(crate) const fn new() -> Self {
+        Debugger { breakpoints: [None;BREAKPOINT_MAX], watchpoints: [None;WATCHPOINT_MAX], watching: false, resume: false, hit: None }
+    }
+
+    pub(crate) fn breakpoint(&self, pos: usize) -> bool {
+        self.breakpoints.contains(&Some(pos))
+    }
+
+    pub(crate) fn access(&mut self, target: WatchTarget, write: Option<A>) {
+        if !self.watching || self.hit.is_some() { return; }
+        self.hit = self.watchpoints.iter().position(|wp| match wp {
+            Some(wp) if wp.target == target => match (wp.condition, write) {
+                (WatchCondition::Read, None) => true,
+                (WatchCondition::Write, Some(_)) => true,
+                (WatchCondition::Value(v), Some(a)) => v == a,
+                _ => false
+            },
+            _ => false
+        });
+    }
+}
+
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
+    /// Stops `run` with `StopReason::Breakpoint` before the instruction at `pos` executes.
+    /// Returns `false` if all `BREAKPOINT_MAX` slots are taken.
+    pub fn add_breakpoint(&mut self, pos: usize) -> bool {
+        if self.debug.breakpoint(pos) { return true; }
+        match self.debug.breakpoints.iter_mut().find(|bp| bp.is_none()) {
+            Some(slot) => { *slot = Some(pos); true }
+            None => false
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, pos: usize) -> bool {
+        match self.debug.breakpoints.iter_mut().find(|bp| **bp == Some(pos)) {
+            Some(slot) => { *slot = None; true }
+            None => false
+        }
+    }
+
+    /// Stops `run` with `StopReason::Watchpoint` after an instruction accessed the watched
+    /// memory cell or register. Returns the watchpoint's id, `None` if all slots are taken.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint<A>) -> Option<usize> {
+        let id = self.debug.watchpoints.iter().position(|wp| wp.is_none())?;
+        self.debug.watchpoints[id] = Some(watchpoint);
+        self.debug.watching = true;
+        Some(id)
+    }
+
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        match self.debug.watchpoints.get_mut(id) {
+            Some(wp) if wp.is_some() => {
+                *wp = None;
+                self.debug.watching = self.debug.watchpoints.iter().any(|wp| wp.is_some());
+                true
+            }
+            _ => false
+        }
+    }
+
+    pub fn clear_debug_points(&mut self) {
+        self.debug = Debugger::new();
+    }
+}
@@ -1,30 +1,44 @@
 use core::fmt::Write;
-use core::mem::size_of;
 
-use crate::{VirtMach, VMAtom, VAtom, opcodes::OpCode, Program, Writer};
+use crate::{Atom, VirtMach, VAtom, opcodes::{OpCode, ExtOpCode, Cond}, Program, Writer};
 
-impl VirtMach <'_> {
-    pub fn decompile <W: Write> (program: &Program, position: usize, mut writer: W) -> usize {
+impl <A: Atom> VirtMach <'_, A> {
+    pub fn decompile <W: Write> (program: &Program<A>, position: usize, mut writer: W) -> usize {
         let mut ret = 1;
         
-        if position >= program.data.len() as usize {
+        if position >= program.code().len() as usize {
             let _ = writer.write_str("?");
             return 0;
         }
         
-        let instructions = program.data;     
+        let instructions = program.code();     
         let byte = instructions[position];
         
         let reg = byte >> 4;
-        let val = if position + 1 + size_of::<VMAtom>() > program.data.len() { 0 } else { instructions[position + 1 ..position + 1 + size_of::<VMAtom>()].as_ref().get_atom() };
+        let ext = if byte & 0x0f == OpCode::EXT as u8 { instructions.get(position + 1).copied() } else { None };
+        if ext.is_some() { ret += 1; }
+        let val_pos = position + ret;
+        let val: A = if val_pos + A::SIZE > program.code().len() { A::ZERO } else { instructions[val_pos ..].as_ref().get_atom() };
 
         let mut buf = [0u8;16];
         let mut arg= Writer::from_buffer(&mut buf);
         let use_reg = |buf: &mut dyn core::fmt::Write| { let _ = buf.write_fmt(format_args!("r{}", reg)); };
-        let mut use_val = |buf: &mut dyn core::fmt::Write| { ret += size_of::<VMAtom>(); let _ = buf.write_fmt(format_args!("#{}", val)); };
-        let use_int = |buf: &mut dyn core::fmt::Write| { let _ = buf.write_fmt(format_args!("{}", reg)); };
+        let mut use_val = |buf: &mut dyn core::fmt::Write| { ret += A::SIZE; let _ = buf.write_fmt(format_args!("#{}", val)); };
+        let use_int = |buf: &mut dyn core::fmt::Write| { let _ = match program.imports().nth(reg as usize) { Some(name) => buf.write_str(name), None => buf.write_fmt(format_args!("{}", reg)) }; };
         let mut use_reg_or_val = |buf: &mut dyn core::fmt::Write| { if reg == 0xf { use_val(buf); } else { use_reg(buf); } };
         
+        let cond = |cond: u8| match cond {
+            x if x == (Cond::LT as u8) => "jlt",
+            x if x == (Cond::LE as u8) => "jle",
+            x if x == (Cond::GT as u8) => "jgt",
+            x if x == (Cond::GE as u8) => "jge",
+            x if x == (Cond::LO as u8) => "jlo",
+            x if x == (Cond::LS as u8) => "jls",
+            x if x == (Cond::HI as u8) => "jhi",
+            x if x == (Cond::HS as u8) => "jhs",
+            _ => "?"
+        };
+        
         let op = match byte & 0x0f {
             x if x == (OpCode::REG as u8) => { use_reg(&mut arg); "reg" }            
             x if x == (OpCode::SET as u8) => { use_reg_or_val(&mut arg); "set" }            
@@ -40,6 +54,39 @@ impl VirtMach <'_> {
             x if x == (OpCode::JPC as u8) => { use_reg_or_val(&mut arg); "jpc" }                        
             x if x == (OpCode::JPS as u8) => { use_reg_or_val(&mut arg); "jps" }                        
             x if x == (OpCode::INT as u8) => { use_int(&mut arg); "int" }                
+            x if x == (OpCode::EXT as u8) => {
+                match ext.unwrap_or(0xff) {
+                    x if x == (ExtOpCode::CMP as u8) => { use_reg_or_val(&mut arg); "cmp" }
+                    x if x == (ExtOpCode::AND as u8) => { use_reg_or_val(&mut arg); "and" }
+                    x if x == (ExtOpCode::OR as u8) => { use_reg_or_val(&mut arg); "or" }
+                    x if x == (ExtOpCode::XOR as u8) => { use_reg_or_val(&mut arg); "xor" }
+                    x if x == (ExtOpCode::SHL as u8) => { use_reg_or_val(&mut arg); "shl" }
+                    x if x == (ExtOpCode::SHR as u8) => { use_reg_or_val(&mut arg); "shr" }
+                    x if x == (ExtOpCode::MUL as u8) => { use_reg_or_val(&mut arg); "mul" }
+                    x if x == (ExtOpCode::INC as u8) => { "inc" }
+                    x if x == (ExtOpCode::DEC as u8) => { "dec" }
+                    x if x == (ExtOpCode::SWP as u8) => { use_reg(&mut arg); "swap" }
+                    x if x == (ExtOpCode::LOX as u8) => { let _ = arg.write_fmt(format_args!("[r{}+#{}]", reg, val)); ret += A::SIZE; "loa" }
+                    x if x == (ExtOpCode::STX as u8) => { let _ = arg.write_fmt(format_args!("[r{}+#{}]", reg, val)); ret += A::SIZE; "sto" }
+                    x if x == (ExtOpCode::LOI as u8) => { let _ = arg.write_fmt(format_args!("[r{}]+", reg)); "loa" }
+                    x if x == (ExtOpCode::STI as u8) => { let _ = arg.write_fmt(format_args!("[r{}]+", reg)); "sto" }
+                    x if x == (ExtOpCode::JCC as u8) => { use_val(&mut arg); cond(reg) }
+                    x if x == (ExtOpCode::DJN as u8) => { let _ = arg.write_fmt(format_args!("r{} ", reg)); use_val(&mut arg); "djn" }
+                    x if x == (ExtOpCode::FAR as u8) => {
+                        ret += 2;
+                        if let Some(bytes) = instructions.get(position + 2 .. position + 4) { let _ = arg.write_fmt(format_args!("@{}", u16::from_le_bytes([bytes[0], bytes[1]]))); }
+                        match reg {
+                            x if x == (OpCode::CAL as u8) => { "cal" }
+                            x if x == (OpCode::JMP as u8) => { "jmp" }
+                            x if x == (OpCode::JPZ as u8) => { "jpz" }
+                            x if x == (OpCode::JPC as u8) => { "jpc" }
+                            x if x == (OpCode::JPS as u8) => { "jps" }
+                            _ => { cond(reg) }
+                        }
+                    }
+                    _ => { "?" }
+                }
+            }
             0x0f => {
                 let op = byte;
                 match op {                    
@@ -47,6 +94,11 @@ impl VirtMach <'_> {
                     x if x == (OpCode::CLR as u8) => { "clr" }                                      
                     x if x == (OpCode::INV as u8) => { "inv" }                                      
                     x if x == (OpCode::NEG as u8) => { "neg" }                                      
+                    x if x == (OpCode::EI as u8) => { "ei" }
+                    x if x == (OpCode::DI as u8) => { "di" }
+                    x if x == (OpCode::RTI as u8) => { "rti" }
+                    x if x == (OpCode::IVT as u8) => { use_val(&mut arg); "ivt" }
+                    x if x == (OpCode::WFI as u8) => { "wfi" }
                     x if x == (OpCode::BRK as u8) => { "brk" }
                     x if x == (OpCode::HLT as u8) => { "hlt" }
                     x if x == (OpCode::END as u8) => { "end" }
@@ -1,6 +1,9 @@
+use core::fmt::{Display, Formatter, Result};
+
 #[derive(Debug)]
 #[derive(PartialEq)]
 #[derive(Clone)]
+#[repr(u8)]
 pub enum RuntimeError {
     NoError,
     MismatchedAtomType,
@@ -14,5 +17,74 @@ pub enum RuntimeError {
     HeapCrash,
     UnhandledInterrupt,
     UnimplementedInterruptFunc,
-    InterruptError
-}
\ No newline at end of file
+    InterruptError,
+    MalformedHeader,
+    UnsupportedVersion,
+    MismatchedEndianness,
+    TruncatedProgram,
+    ChecksumMismatch,
+    MalformedImportTable,
+    MissingInterrupt,
+    InterruptArityMismatch,
+    BufferTooSmall,
+    MalformedSnapshot,
+    MismatchedProgram,
+    ReplayDiverged,
+    IrqOutOfBounds
+}
+
+impl RuntimeError {
+    const ALL: [RuntimeError;26] = [
+        RuntimeError::NoError, RuntimeError::MismatchedAtomType, RuntimeError::IllegalInstruction, RuntimeError::RegisterOutOfBounds,
+        RuntimeError::ProgramOutOfBounds, RuntimeError::MemoryOutOfBounds, RuntimeError::InstructionPointerOutOfBounds,
+        RuntimeError::HeapOverflow, RuntimeError::HeapUnderflow, RuntimeError::HeapCrash, RuntimeError::UnhandledInterrupt,
+        RuntimeError::UnimplementedInterruptFunc, RuntimeError::InterruptError, RuntimeError::MalformedHeader,
+        RuntimeError::UnsupportedVersion, RuntimeError::MismatchedEndianness, RuntimeError::TruncatedProgram,
+        RuntimeError::ChecksumMismatch, RuntimeError::MalformedImportTable, RuntimeError::MissingInterrupt,
+        RuntimeError::InterruptArityMismatch, RuntimeError::BufferTooSmall, RuntimeError::MalformedSnapshot,
+        RuntimeError::MismatchedProgram, RuntimeError::ReplayDiverged, RuntimeError::IrqOutOfBounds
+    ];
+
+    /// A number identifying the error in serialized state, stable across builds.
+    pub fn code(&self) -> u8 {
+        return self.clone() as u8;
+    }
+
+    pub fn from_code(code: u8) -> Option<RuntimeError> {
+        return RuntimeError::ALL.get(code as usize).cloned();
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl core::error::Error for RuntimeError {}
+
+/// Where and on what a `VirtMach` faulted, kept in `VirtMach::fault` until the next reset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    pub error: RuntimeError,
+    /// Position of the faulting instruction in the code.
+    pub pc: usize,
+    /// The raw opcode byte, `None` if no instruction could be fetched.
+    pub opcode: Option<u8>,
+    /// The offending operand: the address, register, jump target or stack pointer.
+    pub operand: Option<i64>,
+    /// Interrupt slot and function number of a failed `int`.
+    pub interrupt: Option<(u8, i64)>
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} at {:04x}", self.error, self.pc)?;
+        if let Some(opcode) = self.opcode { write!(f, ", opcode {:02x}", opcode)?; }
+        if let Some(operand) = self.operand { write!(f, ", operand {}", operand)?; }
+        if let Some((slot, function)) = self.interrupt { write!(f, ", int {} function {}", slot, function)?; }
+        Ok(())
+    }
+}
+
+impl core::error::Error for Fault {}
@@ -0,0 +1,142 @@
+use crate::{Atom, Runtime, RuntimeError, VirtMach};
+
+#[derive(Clone, Copy)]
+enum Change<A: Atom> {
+    None,
+    /// Processor state before an instruction, followed by the changes the instruction made.
+    Step { pc: usize, stack_ptr: usize, act_reg: u8, flags: u8, irq_pending: u8 },
+    Register(u8, A),
+    Memory(usize, A),
+    Vector(u8, Option<usize>)
+}
+
+/// A slot of the ring buffer passed to `VirtMach::set_history`.
+#[derive(Clone, Copy)]
+pub struct HistoryEntry<A: Atom>(Change<A>);
+
+impl <A: Atom> HistoryEntry<A> {
+    pub const EMPTY: HistoryEntry<A> = HistoryEntry(Change::None);
+}
+
+/// Ring buffer of the values overwritten by the recent instructions. When full, the oldest
+/// instruction is dropped as a whole.
+pub(crate) struct History<'a, A: Atom> {
+    buf: &'a mut [HistoryEntry<A>],
+    head: usize,
+    len: usize,
+    steps: usize
+}
+
+impl <'a, A: Atom> History<'a, A> {
+    pub(crate) fn new(buf: &'a mut [HistoryEntry<A>]) -> Self {
+        History { buf, head: 0, len: 0, steps: 0 }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.steps = 0;
+    }
+
+    pub(crate) fn step(&mut self, pc: usize, stack_ptr: usize, act_reg: usize, flags: u8, irq_pending: u8) {
+        if self.buf.is_empty() { return; }
+        self.push(Change::Step { pc, stack_ptr, act_reg: act_reg as u8, flags, irq_pending });
+        self.steps += 1;
+    }
+
+    pub(crate) fn register(&mut self, reg: usize, old: A) {
+        if self.buf.is_empty() { return; }
+        self.push(Change::Register(reg as u8, old));
+    }
+
+    pub(crate) fn memory(&mut self, addr: usize, old: A) {
+        if self.buf.is_empty() { return; }
+        self.push(Change::Memory(addr, old));
+    }
+
+    pub(crate) fn vector(&mut self, line: usize, old: Option<usize>) {
+        if self.buf.is_empty() { return; }
+        self.push(Change::Vector(line as u8, old));
+    }
+
+    fn push(&mut self, change: Change<A>) {
+        let cap = self.buf.len();
+        if self.len == cap {
+            self.drop_oldest();
+        }
+        self.buf[self.head] = HistoryEntry(change);
+        self.head = (self.head + 1) % cap;
+        self.len += 1;
+    }
+
+    fn drop_oldest(&mut self) {
+        let cap = self.buf.len();
+        let tail = (self.head + cap - self.len) % cap;
+        if let Change::Step { .. } = self.buf[tail].0 { self.steps -= 1; }
+        self.len -= 1;
+        while self.len > 0 && !matches!(self.buf[(self.head + cap - self.len) % cap].0, Change::Step { .. }) {
+            self.len -= 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<Change<A>> {
+        if self.len == 0 { return None; }
+        let cap = self.buf.len();
+        self.head = (self.head + cap - 1) % cap;
+        self.len -= 1;
+        if let Change::Step { .. } = self.buf[self.head].0 { self.steps -= 1; }
+        Some(self.buf[self.head].0)
+    }
+}
+
+impl <'a, A: Atom, const REGS: usize> VirtMach <'a, A, REGS> {
+    /// Records the changes of every executed instruction into `buf`, so that the latest ones
+    /// can be undone with `step_back`. Changes an interrupt makes to `memory` or `registers`
+    /// without going through the stack are not recorded.
+    pub fn set_history(&mut self, buf: &'a mut [HistoryEntry<A>]) {
+        self.history = History::new(buf);
+    }
+
+    /// The number of instructions `step_back` can undo.
+    pub fn history_depth(&self) -> usize {
+        self.history.steps
+    }
+
+    /// Undoes the last executed instruction and halts the VM there. Returns `false` if the
+    /// history holds no more instructions.
+    pub fn step_back(&mut self) -> bool {
+        if self.history.steps == 0 { return false; }
+        while let Some(change) = self.history.pop() {
+            match change {
+                Change::Register(reg, a) => { self.registers[reg as usize] = a; }
+                Change::Memory(addr, a) => { self.memory[addr] = a; }
+                Change::Vector(line, vector) => { self.irq.vectors[line as usize] = vector; }
+                Change::Step { pc, stack_ptr, act_reg, flags, irq_pending } => {
+                    self.processor.prog_cnt = pc;
+                    self.processor.stack_ptr = stack_ptr;
+                    self.processor.act_reg = act_reg as usize;
+                    self.processor.set_flags(flags);
+                    self.irq.pending = irq_pending;
+                    break;
+                }
+                Change::None => {}
+            }
+        }
+        self.cycle_cnt = self.cycle_cnt.saturating_sub(1);
+        self.state = Runtime::Hlt;
+        self.error = RuntimeError::NoError;
+        self.fault = None;
+        self.debug.resume = true;
+        self.debug.hit = None;
+        return true;
+    }
+
+    /// Steps back until the VM is at program position `pc`. Returns `false` if the history
+    /// ran out before.
+    pub fn run_back_to(&mut self, pc: usize) -> bool {
+        while self.step_back() {
+            if self.processor.prog_cnt == pc { return true; }
+        }
+        return false;
+    }
+}
@@ -1,5 +1,5 @@
 use cfg_block::cfg_block;
-use crate::{ VirtMach, VMAtom};
+use crate::{ Atom, VirtMach, VMAtom, REG_MAX};
 
 mod dummy;
 pub use dummy::Interrupt as Dummy;
@@ -10,8 +10,8 @@ pub use proc::Interrupt as Proc;
 mod math;
 pub use math::Interrupt as Math;
 
-mod surface;
-pub use surface::MAP as SurfaceMap;
+mod journal;
+pub use journal::{Journal, Recorder, Replayer};
 
 cfg_block! {
     #[cfg(feature = "random")] {
@@ -20,30 +20,51 @@ cfg_block! {
     }
 }
 
-#[derive(Clone, Copy)]
+/// Describes an interrupt to the compiler, which resolves `name.function(...)` calls
+/// against it, and to the VM, which checks the stack against the declared arity.
+#[derive(Clone, Copy, Debug)]
 pub struct SoftInterruptDef <'a> {
     pub name: &'a str,
+    /// An empty list leaves the interrupt's stack usage unchecked.
     pub functions: &'a [SoftInterruptFunction <'a>]
 }
 
-#[derive(Clone, Copy)]
+/// A function of an interrupt, selected by pushing `no` before `int`. `arguments` values
+/// are popped and `returns` values pushed by the call.
+#[derive(Clone, Copy, Debug)]
 pub struct SoftInterruptFunction <'a> {
-    pub no: VMAtom,
+    pub no: u8,
     pub name: &'a str,    
     pub arguments: usize,
     pub returns: usize,
     pub help: &'a str
 }
 
-pub const BASE_INTERRUPT_MAPS: &[(&str, &str)] = &[
-    proc::MAP,
-    math::MAP,
+impl <'a> SoftInterruptFunction <'a> {
+    pub const fn new(no: u8, name: &'a str, arguments: usize, returns: usize, help: &'a str) -> Self {
+        SoftInterruptFunction { no, name, arguments, returns, help }
+    }
+}
+
+impl SoftInterruptDef <'_> {
+    pub fn function<A: Atom>(&self, no: A) -> Option<&SoftInterruptFunction<'_>> {
+        self.functions.iter().find(|f| f.no as i64 == no.to_i64())
+    }
+}
+
+pub const BASE_INTERRUPT_DEFS: &[SoftInterruptDef<'static>] = &[
+    proc::DEF,
+    math::DEF,
     #[cfg(feature = "random")]
-    random::MAP
+    random::DEF
 ];
 
-pub trait SoftInterrupt {    
-    fn name(&self) -> &str;    
+pub trait SoftInterrupt <A: Atom = VMAtom, const REGS: usize = REG_MAX> {    
+    fn def(&self) -> SoftInterruptDef<'_>;
+
+    fn name(&self) -> &str {
+        return self.def().name;
+    }
     
-    fn call(&mut self, vm: &mut VirtMach);
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>);
 }
\ No newline at end of file
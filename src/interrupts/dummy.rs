@@ -1,16 +1,15 @@
-use crate::{VirtMach, interrupts::{ SoftInterrupt }};
+use crate::{Atom, VirtMach, interrupts::{ SoftInterrupt, SoftInterruptDef }};
 
-#[allow(dead_code)]
-pub const MAP: (&str, &str) = ( "dummy", "");
+pub const DEF: SoftInterruptDef<'static> = SoftInterruptDef { name: "dummy", functions: &[] };
 
 pub struct Interrupt {}
 
-impl SoftInterrupt for Interrupt {
-    fn name(&self) -> &str {
-        return "dummy";
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Interrupt {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return DEF;
     }
 
-    fn call(&mut self, _vm: &mut VirtMach) {        
+    fn call(&mut self, _vm: &mut VirtMach<'_, A, REGS>) {        
     }
 
 }
@@ -0,0 +1,240 @@
+use core::cell::RefCell;
+
+use crate::{Atom, VirtMach, RuntimeError, interrupts::{ SoftInterrupt, SoftInterruptDef }};
+
+/// The effects of interrupt calls on the stack, memory, flags and error, in call order.
+/// `Recorder`s append to it, `Replayer`s read it back.
+///
+/// Each entry holds the cycle count since the previous entry, the flags, the error code, the
+/// number of values popped and pushed, the pushed atoms, the number of memory cells written
+/// and their addresses and values. Counts and addresses are LEB128, atoms little endian.
+///
+/// While recording a call, the cells below the stack are copied to the unused end of the
+/// buffer to find the ones the call writes, so it needs that much room to spare.
+pub struct Journal <'b> {
+    buf: &'b mut [u8],
+    len: usize,
+    /// Writes stop here, to keep the copy of the memory intact until it was compared.
+    limit: usize,
+    pos: usize,
+    cycle: usize
+}
+
+impl <'b> Journal <'b> {
+    /// An empty journal recording into `buf`.
+    pub fn new(buf: &'b mut [u8]) -> Self {
+        let limit = buf.len();
+        Journal { buf, len: 0, limit, pos: 0, cycle: 0 }
+    }
+
+    /// A journal replaying a recording, e.g. `as_bytes()` of a field session.
+    pub fn load(buf: &'b mut [u8]) -> Self {
+        let len = buf.len();
+        Journal { buf, len, limit: len, pos: 0, cycle: 0 }
+    }
+
+    /// The recorded entries.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Starts replaying from the first entry.
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+        self.cycle = 0;
+    }
+
+    /// Copies `heap` to the end of the buffer, returning where the copy starts.
+    fn stash<A: Atom>(&mut self, heap: &[A]) -> Result<usize, RuntimeError> {
+        let size = heap.len() * A::SIZE;
+        if self.buf.len() - self.len < size { return Err(RuntimeError::BufferTooSmall); }
+        let stash = self.buf.len() - size;
+        for (i, a) in heap.iter().enumerate() {
+            a.to_le_bytes(&mut self.buf[stash + i * A::SIZE ..]);
+        }
+        return Ok(stash);
+    }
+
+    /// Appends an entry for the `pushed` values on top of the stack, with the cells of the
+    /// first `heap` that differ from the copy at `stash`.
+    fn record<A: Atom, const REGS: usize>(&mut self, cycle: usize, vm: &VirtMach<'_, A, REGS>, popped: usize, pushed: usize, heap: usize, stash: usize) -> Result<(), RuntimeError> {
+        let start = self.len;
+        let (flags, error) = (vm.processor.flags(), &vm.error);
+        let top = vm.processor.stack_ptr + 1;
+        let pushed = &vm.memory[top .. top + pushed];
+        let heap = &vm.memory[..heap];
+        let stashed = |buf: &[u8], addr: usize| A::from_le_bytes(&buf[stash + addr * A::SIZE ..]);
+        let written = heap.iter().enumerate().filter(|(addr, a)| stashed(self.buf, *addr) != **a).count();
+
+        self.limit = stash;
+        let mut fits = self.put_varint(cycle.saturating_sub(self.cycle) as u64)
+            && self.put(flags) && self.put(error.code())
+            && self.put_varint(popped as u64) && self.put_varint(pushed.len() as u64)
+            && pushed.iter().rev().all(|a| self.put_atom(*a))
+            && self.put_varint(written as u64);
+        for (addr, a) in heap.iter().enumerate() {
+            if !fits { break; }
+            if stashed(self.buf, addr) == *a { continue; }
+            // the copies of the cells compared so far may be overwritten
+            self.limit = stash + (addr + 1) * A::SIZE;
+            fits = self.put_varint(addr as u64) && self.put_atom(*a);
+        }
+        self.limit = self.buf.len();
+        if !fits {
+            self.len = start;
+            return Err(RuntimeError::BufferTooSmall);
+        }
+        self.cycle = cycle;
+        return Ok(());
+    }
+
+    fn replay<A: Atom, const REGS: usize>(&mut self, vm: &mut VirtMach<'_, A, REGS>) -> Result<(), RuntimeError> {
+        let start = self.pos;
+        let entry = (|| Some((self.get_varint()?, self.get()?, self.get()?, self.get_varint()? as usize, self.get_varint()? as usize)))();
+        let Some((delta, flags, error, popped, pushed)) = entry else { self.pos = start; return Err(RuntimeError::ReplayDiverged); };
+        let error = RuntimeError::from_code(error);
+        if self.cycle + delta as usize != vm.cycle_cnt || error.is_none() || self.len - self.pos < pushed * A::SIZE {
+            self.pos = start;
+            return Err(RuntimeError::ReplayDiverged);
+        }
+        let values = self.pos;
+        self.pos += pushed * A::SIZE;
+
+        // check all writes before applying any
+        let writes = self.pos;
+        let valid = (|| {
+            for _ in 0 .. self.get_varint()? {
+                let addr = self.get_varint()? as usize;
+                if addr >= vm.memory.len() || self.len - self.pos < A::SIZE { return None; }
+                self.pos += A::SIZE;
+            }
+            Some(())
+        })();
+        if valid.is_none() {
+            self.pos = start;
+            return Err(RuntimeError::ReplayDiverged);
+        }
+        let end = self.pos;
+
+        for _ in 0 .. popped { vm.stack_pop(); }
+        for i in 0 .. pushed {
+            vm.stack_push(A::from_le_bytes(&self.buf[values + i * A::SIZE ..]));
+        }
+        self.pos = writes;
+        for _ in 0 .. self.get_varint().unwrap_or(0) {
+            let addr = self.get_varint().unwrap_or(0) as usize;
+            vm.mem_write(addr, A::from_le_bytes(&self.buf[self.pos..]));
+            self.pos += A::SIZE;
+        }
+        self.pos = end;
+        vm.processor.set_flags(flags);
+        if let Some(error) = error && error != RuntimeError::NoError { vm.error = error; }
+        self.cycle = vm.cycle_cnt;
+        return Ok(());
+    }
+
+    fn put(&mut self, byte: u8) -> bool {
+        if self.len >= self.limit { return false; }
+        let Some(b) = self.buf.get_mut(self.len) else { return false; };
+        *b = byte;
+        self.len += 1;
+        true
+    }
+
+    fn put_varint(&mut self, mut v: u64) -> bool {
+        while v >= 0x80 {
+            if !self.put(v as u8 | 0x80) { return false; }
+            v >>= 7;
+        }
+        self.put(v as u8)
+    }
+
+    fn put_atom<A: Atom>(&mut self, a: A) -> bool {
+        if self.len + A::SIZE > self.limit { return false; }
+        let Some(bytes) = self.buf.get_mut(self.len .. self.len + A::SIZE) else { return false; };
+        a.to_le_bytes(bytes);
+        self.len += A::SIZE;
+        true
+    }
+
+    fn get(&mut self) -> Option<u8> {
+        if self.pos >= self.len { return None; }
+        self.pos += 1;
+        Some(self.buf[self.pos - 1])
+    }
+
+    fn get_varint(&mut self) -> Option<u64> {
+        let mut v = 0u64;
+        for shift in (0 .. 64).step_by(7) {
+            let b = self.get()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 { return Some(v); }
+        }
+        None
+    }
+}
+
+/// Calls `inner` and appends what the call pushed and wrote to memory to a shared `Journal`.
+/// The pushed values are the returns declared in `inner`'s definition, or the stack growth
+/// if it declares none. A full journal fails the call with `BufferTooSmall`.
+pub struct Recorder <'i, 'j, 'b, A: Atom, const REGS: usize> {
+    inner: &'i mut dyn SoftInterrupt<A, REGS>,
+    journal: &'j RefCell<Journal<'b>>
+}
+
+impl <'i, 'j, 'b, A: Atom, const REGS: usize> Recorder <'i, 'j, 'b, A, REGS> {
+    pub fn new(inner: &'i mut dyn SoftInterrupt<A, REGS>, journal: &'j RefCell<Journal<'b>>) -> Self {
+        Recorder { inner, journal }
+    }
+}
+
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Recorder <'_, '_, '_, A, REGS> {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return self.inner.def();
+    }
+
+    fn name(&self) -> &str {
+        return self.inner.name();
+    }
+
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>) {
+        let cycle = vm.cycle_cnt;
+        let depth = vm.stack_depth();
+        let returns = self.inner.def().function(vm.stack_peek()).map(|f| f.returns).unwrap_or(0);
+        let heap = vm.processor.stack_ptr;
+        let stash = match self.journal.borrow_mut().stash(&vm.memory[..heap]) {
+            Ok(stash) => stash,
+            Err(err) => { vm.error = err; return; }
+        };
+        self.inner.call(vm);
+
+        let after = vm.stack_depth();
+        let pushed = returns.max(after.saturating_sub(depth)).min(after);
+        let heap = heap.min(vm.processor.stack_ptr);
+        let res = self.journal.borrow_mut().record(cycle, vm, depth + pushed - after, pushed, heap, stash);
+        if let Err(err) = res && vm.error == RuntimeError::NoError { vm.error = err; }
+    }
+}
+
+/// Stands in for the interrupt described by `def`, replaying its calls from a `Journal`
+/// instead. A call at another cycle than recorded fails with `ReplayDiverged`.
+pub struct Replayer <'d, 'j, 'b> {
+    def: SoftInterruptDef<'d>,
+    journal: &'j RefCell<Journal<'b>>
+}
+
+impl <'d, 'j, 'b> Replayer <'d, 'j, 'b> {
+    pub fn new(def: SoftInterruptDef<'d>, journal: &'j RefCell<Journal<'b>>) -> Self {
+        Replayer { def, journal }
+    }
+}
+
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Replayer <'_, '_, '_> {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return self.def;
+    }
+
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>) {
+        if let Err(err) = self.journal.borrow_mut().replay(vm) { vm.error = err; }
+    }
+}
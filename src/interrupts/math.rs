@@ -1,44 +1,46 @@
-use crate::{RuntimeError, VirtMach, interrupts::{ SoftInterrupt }};
+use crate::{Atom, RuntimeError, VirtMach, interrupts::{ SoftInterrupt, SoftInterruptDef, SoftInterruptFunction }};
 
-#[allow(dead_code)]
-pub const MAP: (&str, &str) = (
-"math",
-"0,  and, 2, 1,
- 1,  or,  2, 1,
- 2,  xor, 2, 1,
- 3,  not, 1, 1,
- 4,  lsh, 2, 1,
- 5,  rsh, 2, 1,
- 6,  mul, 2, 1,
- 7,  div, 2, 1,
- 8,  mod, 2, 1,
- 9,  pow, 2, 1,
- 10, sqr, 1, 1,
-");
+pub const DEF: SoftInterruptDef<'static> = SoftInterruptDef { name: "math", functions: &[
+    SoftInterruptFunction::new( 0, "and", 2, 1, "a & b"),
+    SoftInterruptFunction::new( 1, "or",  2, 1, "a | b"),
+    SoftInterruptFunction::new( 2, "xor", 2, 1, "a ^ b"),
+    SoftInterruptFunction::new( 3, "not", 1, 1, "!a"),
+    SoftInterruptFunction::new( 4, "lsh", 2, 1, "a << b"),
+    SoftInterruptFunction::new( 5, "rsh", 2, 1, "a >> b"),
+    SoftInterruptFunction::new( 6, "mul", 2, 1, "a * b"),
+    SoftInterruptFunction::new( 7, "div", 2, 1, "a / b"),
+    SoftInterruptFunction::new( 8, "mod", 2, 1, "a % b"),
+    SoftInterruptFunction::new( 9, "pow", 2, 1, "a to the power of b"),
+    SoftInterruptFunction::new(10, "sqr", 1, 1, "square root of a")
+] };
 
+/// Results wrap around the atom's width, with carry set when `mul`, `div`, `mod` or `pow`
+/// overflow. `rsh` is arithmetic on signed and logical on unsigned atoms, shifting by the
+/// atom's width or more (or a negative count) shifts out all bits. `sqr` is the integer
+/// square root; it and `pow` fail with `InterruptError` on negative arguments.
 pub struct Interrupt {}
 
-impl SoftInterrupt for Interrupt {
-    fn name(&self) -> &str {
-        return "math";
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Interrupt {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return DEF;
     }
 
-    fn call(&mut self, vm: &mut VirtMach) {
-        let op = vm.stack_pop();        
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>) {
+        let op = vm.stack_pop().to_i64();        
         match op {
-            3 | 9 => {
+            3 | 10 => {
                 let a = vm.stack_pop();
                 let res;                
                 match op {
                     3 => { res = ( !a, false); }
-                    9 => { res = (0, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }                    
-                    _ => { res = (0, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }
+                    10 => { res = match u64::try_from(a.to_i64()) { Ok(v) => (A::from_i64(v.isqrt() as i64), false), Err(_) => { vm.error = RuntimeError::InterruptError; (A::ZERO, false) } }; }
+                    _ => { res = (A::ZERO, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }
                 }
-                vm.processor.zero = res.0 == 0;
+                vm.processor.zero = res.0 == A::ZERO;
                 vm.processor.carry = res.1;
                 vm.stack_push(res.0);                  
             }
-            0 .. 3 | 4 .. 9 => {
+            0 .. 3 | 4 ..= 9 => {
                 let a = vm.stack_pop();
                 let b = vm.stack_pop();
                 let res;                
@@ -46,15 +48,15 @@ impl SoftInterrupt for Interrupt {
                     0  => { res = (a & b, false); }
                     1  => { res = (a | b, false); }
                     2  => { res = (a ^ b, false); }
-                    4  => { res = (a << (b as u32), false); }
-                    5  => { res = (a >> (b as u32), false); }
+                    4  => { res = (if (0..A::BITS as i64).contains(&b.to_i64()) { a << (b.to_i64() as u32) } else { A::ZERO }, false); }
+                    5  => { res = (if (0..A::BITS as i64).contains(&b.to_i64()) { a >> (b.to_i64() as u32) } else if A::SIGNED && a.sign_bit() { !A::ZERO } else { A::ZERO }, false); }
                     6  => { res = a.overflowing_mul(b); }                    
-                    7  => { res = if b != 0 { a.overflowing_div(b) } else { (0, false) }; if b == 0 { vm.error = RuntimeError::InterruptError; } }
-                    8  => { res = if b != 0 { (a % b, false) } else { (0, false) }; if b == 0 { vm.error = RuntimeError::InterruptError; } }
-                    9  => { res = a.overflowing_pow(b as u32); }                      
-                    _ => { res = (0, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }
+                    7  => { res = if b != A::ZERO { a.overflowing_div(b) } else { (A::ZERO, false) }; if b == A::ZERO { vm.error = RuntimeError::InterruptError; } }
+                    8  => { res = if b != A::ZERO { a.overflowing_rem(b) } else { (A::ZERO, false) }; if b == A::ZERO { vm.error = RuntimeError::InterruptError; } }
+                    9  => { res = if b.to_i64() >= 0 { a.overflowing_pow(u32::try_from(b.to_i64()).unwrap_or(u32::MAX)) } else { (A::ZERO, false) }; if b.to_i64() < 0 { vm.error = RuntimeError::InterruptError; } }                      
+                    _ => { res = (A::ZERO, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }
                 }
-                vm.processor.zero = res.0 == 0;
+                vm.processor.zero = res.0 == A::ZERO;
                 vm.processor.carry = res.1;
                 vm.stack_push(res.0);  
                 
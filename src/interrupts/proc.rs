@@ -1,32 +1,30 @@
-use crate::{VirtMach, VMAtom, RuntimeError, interrupts::{ SoftInterrupt }};
+use crate::{Atom, VirtMach, RuntimeError, interrupts::{ SoftInterrupt, SoftInterruptDef, SoftInterruptFunction }};
 
-#[allow(dead_code)]
-pub const MAP: (&str, &str) = (
-"proc",
-"0, version,   0, 3,
- 1, atom_size, 0, 1,
- 2, mem_size,  0, 1,
- 3, stack_ptr, 0, 1,
- 4, prog_cnt,  0, 1,
- 5, cycle_cnt, 0, 1,
-");
+pub const DEF: SoftInterruptDef<'static> = SoftInterruptDef { name: "proc", functions: &[
+    SoftInterruptFunction::new(0, "version",   0, 3, "major, minor and patch version"),
+    SoftInterruptFunction::new(1, "atom_size", 0, 1, "size of an atom in bytes"),
+    SoftInterruptFunction::new(2, "mem_size",  0, 1, "memory cells, including the stack"),
+    SoftInterruptFunction::new(3, "stack_ptr", 0, 1, "stack pointer"),
+    SoftInterruptFunction::new(4, "prog_cnt",  0, 1, "program counter"),
+    SoftInterruptFunction::new(5, "cycle_cnt", 0, 1, "instructions executed since reset")
+] };
 
 pub struct Interrupt {}
 
-impl SoftInterrupt for Interrupt {
-    fn name(&self) -> &str {
-        return "proc";
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Interrupt {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return DEF;
     }
 
-    fn call(&mut self, vm: &mut VirtMach) {
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>) {
         let op = vm.stack_pop();        
-        match op {
-            0 => { vm.stack_push(0); vm.stack_push(1); vm.stack_push(0); }            
-            1 => { vm.stack_push(core::mem::size_of::<VMAtom>() as VMAtom); }
-            2 => { vm.stack_push(crate::MEM_SIZE as VMAtom); }
-            3 => { vm.stack_push(vm.processor.stack_ptr as VMAtom); }
-            4 => { vm.stack_push(vm.processor.prog_cnt as VMAtom); }
-            5 => { vm.stack_push(vm.cycle_cnt as VMAtom); }
+        match op.to_i64() {
+            0 => { vm.stack_push(A::ZERO); vm.stack_push(A::from_i64(1)); vm.stack_push(A::ZERO); }            
+            1 => { vm.stack_push(A::from_i64(A::SIZE as i64)); }
+            2 => { vm.stack_push(A::from_usize(vm.memory.len()).unwrap_or(A::MAX)); }
+            3 => { vm.stack_push(A::from_i64(vm.processor.stack_ptr as i64)); }
+            4 => { vm.stack_push(A::from_i64(vm.processor.prog_cnt as i64)); }
+            5 => { vm.stack_push(A::from_i64(vm.cycle_cnt as i64)); }
             _ => { vm.error = RuntimeError::UnimplementedInterruptFunc; }
         }
     }
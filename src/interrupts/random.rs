@@ -1,39 +1,81 @@
-use crate::{VirtMach, VMAtom, RuntimeError, interrupts::{ SoftInterrupt }};
+use crate::{Atom, VirtMach, RuntimeError, interrupts::{ SoftInterrupt, SoftInterruptDef, SoftInterruptFunction }};
 
-#[allow(dead_code)]
-pub const MAP: (&str, &str) = (
-"random","0, range, 2, 1,
-");
+pub const DEF: SoftInterruptDef<'static> = SoftInterruptDef { name: "random", functions: &[
+    SoftInterruptFunction::new(0, "range", 2, 1, "random value between a and b, inclusive"),
+    SoftInterruptFunction::new(1, "seed", 1, 0, "restarts the sequence from seed a"),
+    SoftInterruptFunction::new(2, "next", 0, 1, "random value over the full atom range"),
+    SoftInterruptFunction::new(3, "shuffle", 2, 0, "shuffles the b memory cells starting at address a")
+] };
 
-use nostd_structs::algos::rand;
+use nostd_structs::algos::rand::lcg::LcgRng;
 
-static mut SEED: u64 = 0;
+/// A random generator owned by the interrupt, so each VM draws its own reproducible sequence.
+pub struct Interrupt {
+    rng: LcgRng
+}
+
+impl Interrupt {
+    pub fn new(seed: u64) -> Self {
+        Interrupt { rng: Self::rng(seed) }
+    }
+
+    /// `LcgRng` overflows on states of 32 bits or more, so only the low bits seed it.
+    fn rng(seed: u64) -> LcgRng {
+        LcgRng::new(seed & 0xffff_ffff)
+    }
 
-pub struct Interrupt {}
+    fn next_u64(&mut self) -> u64 {
+        (self.rng.next() << 32) | self.rng.next()
+    }
+}
 
-impl SoftInterrupt for Interrupt {
-    fn name(&self) -> &str {
-        return "math";
+impl <A: Atom, const REGS: usize> SoftInterrupt<A, REGS> for Interrupt {
+    fn def(&self) -> SoftInterruptDef<'_> {
+        return DEF;
     }
-    
-    fn call(&mut self, vm: &mut VirtMach) {
-        let op = vm.stack_pop();        
-        let a = vm.stack_pop();
-        let b = vm.stack_pop();
-        let res;                
-        match op {            
+
+    fn call(&mut self, vm: &mut VirtMach<'_, A, REGS>) {
+        let op = vm.stack_pop().to_i64();
+        match op {
             0 => {
-                    let len = a.saturating_sub(b).abs() + 1;
-                    let min = if a <= b { a } else { b };                                                      
-                    let value = rand::lcg::LcgRng::new(unsafe { SEED }.wrapping_add((vm.cycle_cnt as u64).wrapping_add(vm.program.id.as_ptr() as u64))).next();
-                    unsafe { SEED = value; }
-                    res = ( min + ((value % len as u64)) as VMAtom, false);                        
+                // i128, as the span of i64 atoms does not fit an i64
+                let a = vm.stack_pop().to_i64() as i128;
+                let b = vm.stack_pop().to_i64() as i128;
+                let len = (a - b).unsigned_abs() + 1;
+                let min = if a <= b { a } else { b };
+                let res = A::from_i64((min + (self.next_u64() as u128 % len) as i128) as i64);
+                vm.processor.zero = res == A::ZERO;
+                vm.processor.carry = false;
+                vm.stack_push(res);
+            }
+            1 => {
+                let seed = vm.stack_pop().to_i64();
+                self.rng = Self::rng(seed as u64);
+            }
+            2 => {
+                let res = A::from_i64(self.next_u64() as i64);
+                vm.processor.zero = res == A::ZERO;
+                vm.processor.carry = false;
+                vm.stack_push(res);
+            }
+            3 => {
+                let start = vm.stack_pop();
+                let len = vm.stack_pop();
+                match (start.to_usize(), len.to_usize()) {
+                    (Some(start), Some(len)) if start.checked_add(len).is_some_and(|end| end <= vm.processor.stack_ptr) => {
+                        for i in (1 .. len).rev() {
+                            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+                            if i == j { continue; }
+                            let (a, b) = (vm.mem_read(start + i), vm.mem_read(start + j));
+                            vm.mem_write(start + i, b);
+                            vm.mem_write(start + j, a);
+                        }
+                    }
+                    _ => { vm.error = RuntimeError::MemoryOutOfBounds; vm.operand = Some(start.to_i64()); }
                 }
-            _ => { res = (0, false); vm.error = RuntimeError::UnimplementedInterruptFunc; }
+            }
+            _ => { vm.error = RuntimeError::UnimplementedInterruptFunc; }
         }
-        vm.processor.zero = res.0 == 0;
-        vm.processor.carry = res.1;
-        vm.stack_push(res.0);                                      
     }
 
 }
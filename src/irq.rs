@@ -0,0 +1,92 @@
+use crate::{Atom, Fault, Runtime, RuntimeError, VirtMach};
+use crate::observer::observe;
+
+/// Number of IRQ lines a host can raise.
+pub const IRQ_MAX: usize = 4;
+
+/// Handler addresses registered by `ivt`, the lines raised by the host and the host clock
+/// `wfi` deadlines refer to.
+#[derive(Clone, Copy)]
+pub(crate) struct Irq {
+    pub(crate) vectors: [Option<usize>;IRQ_MAX],
+    pub(crate) pending: u8,
+    pub(crate) clock: u64,
+    pub(crate) deadline: Option<u64>
+}
+
+impl Irq {
+    pub(crate) const fn new() -> Self {
+        Irq { vectors: [None;IRQ_MAX], pending: 0, clock: 0, deadline: None }
+    }
+}
+
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
+    /// Requests the handler the program registered for `line`. It runs after the current
+    /// `step` once IRQs are enabled, lower lines first. Returns `false` if the line has no
+    /// handler.
+    pub fn raise_irq(&mut self, line: usize) -> bool {
+        match self.irq.vectors.get(line) {
+            Some(Some(_)) => { self.irq.pending |= 1 << line; true }
+            _ => false
+        }
+    }
+
+    /// Whether `line` was raised and its handler has not been entered yet.
+    pub fn irq_pending(&self, line: usize) -> bool {
+        line < IRQ_MAX && self.irq.pending & (1 << line) != 0
+    }
+
+    /// Sets the host clock, in host-defined ticks. A `wfi` with a timeout ends once the
+    /// clock reaches its deadline.
+    pub fn set_clock(&mut self, now: u64) {
+        self.irq.clock = now;
+    }
+
+    /// The clock value at which the current `wfi` times out, `None` if the VM is not waiting
+    /// or waits without a timeout. Hosts can sleep until then or until they raise an IRQ.
+    pub fn wfi_deadline(&self) -> Option<u64> {
+        if self.state != Runtime::Wfi { return None; }
+        self.irq.deadline
+    }
+
+    /// Ends a `wfi`, the program continues with the next instruction.
+    pub fn wake(&mut self) {
+        if self.state == Runtime::Wfi {
+            self.state = Runtime::Run;
+            self.irq.deadline = None;
+        }
+    }
+
+    /// Ends a `wfi` if an IRQ is pending, entering its handler if IRQs are enabled, or if
+    /// the deadline passed.
+    pub(crate) fn wake_on_irq(&mut self) {
+        if self.state != Runtime::Wfi { return; }
+        if self.irq.pending != 0 || self.irq.deadline.is_some_and(|deadline| self.irq.clock >= deadline) {
+            self.wake();
+            self.deliver_irq();
+        }
+    }
+
+    /// Enters the handler of the lowest pending line, pushing the status (zero, carry, sign and
+    /// overflow in the low, active register in the high nibble) and the return position for
+    /// `rti` and disabling IRQs, which `rti` enables again.
+    pub(crate) fn deliver_irq(&mut self) {
+        if !self.processor.irq_enabled || self.irq.pending == 0 || self.state != Runtime::Run { return; }
+        let line = self.irq.pending.trailing_zeros() as usize;
+        let Some(vector) = self.irq.vectors.get(line).copied().flatten() else { return; };
+
+        match A::from_usize(self.processor.prog_cnt) {
+            Some(ret) => { self.stack_push(A::from_i64((self.processor.flags() & 0x0f | (self.processor.act_reg as u8) << 4) as i64)); self.stack_push(ret); }
+            None => { self.error = RuntimeError::InstructionPointerOutOfBounds; self.operand = Some(self.processor.prog_cnt as i64); }
+        }
+        if self.error != RuntimeError::NoError {
+            self.state = Runtime::Err;
+            self.fault = Some(Fault { error: self.error.clone(), pc: self.processor.prog_cnt, opcode: None, operand: self.operand.take(), interrupt: None });
+            observe!(self, fault(self.fault.as_ref().unwrap()));
+            return;
+        }
+        self.irq.pending &= !(1 << line);
+        self.processor.irq_enabled = false;
+        self.processor.prog_cnt = vector;
+    }
+}
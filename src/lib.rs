@@ -1,9 +1,11 @@
 #![no_std]
+// `return` statements and upper case mnemonics are the house style
+#![allow(clippy::needless_return, clippy::upper_case_acronyms)]
 
 use cfg_block::cfg_block;
 
-const MEM_SIZE:usize = 23;
-const REG_MAX:usize  = 15;
+/// Number of registers addressable by the bytecode (`r0` - `r14`), the upper bound for `VirtMach`'s register file.
+pub const REG_MAX:usize  = 15;
 
 mod atom;
 mod opcodes;
@@ -11,14 +13,28 @@ mod processor;
 mod virtmach;
 mod errors;
 mod program;
+pub mod binary;
 mod writer;
 mod decompile;
 mod reporting;
+mod debug;
+mod history;
+mod irq;
+mod observer;
+mod snapshot;
 pub mod interrupts;
 
 pub use atom::*;
 pub use virtmach::*;
 pub use processor::*;
+pub use observer::Observer;
+pub use history::HistoryEntry;
+pub use irq::IRQ_MAX;
+pub use snapshot::SNAPSHOT_VERSION;
+pub use debug::{Watchpoint, WatchTarget, WatchCondition, BREAKPOINT_MAX, WATCHPOINT_MAX};
+
+#[cfg(feature = "macros")]
+pub use virtmach_macros::interrupt;
 
 cfg_block!{
     #[cfg(feature="std")] {        
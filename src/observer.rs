@@ -0,0 +1,40 @@
+use crate::{Atom, Fault};
+
+/// Callbacks on the execution of a `VirtMach`, set with `VirtMach::set_observer`. All
+/// hooks default to doing nothing. Without the `observer` feature the VM contains no
+/// hook calls at all.
+pub trait Observer<A: Atom> {
+    /// Before the instruction `opcode` at program position `pc` executes.
+    fn before_instruction(&mut self, _pc: usize, _opcode: u8) {}
+
+    /// A store to memory cell `addr`, replacing `old` with `new`. Stack pushes are reported
+    /// by `stack_push` instead.
+    fn memory_write(&mut self, _addr: usize, _old: A, _new: A) {}
+
+    /// `val` was pushed into memory cell `addr`.
+    fn stack_push(&mut self, _addr: usize, _val: A) {}
+
+    /// `val` was popped from memory cell `addr`.
+    fn stack_pop(&mut self, _addr: usize, _val: A) {}
+
+    /// Before the interrupt bound to `slot` is called with function number `function`.
+    fn interrupt_enter(&mut self, _slot: u8, _function: i64) {}
+
+    /// After the interrupt bound to `slot` returned.
+    fn interrupt_exit(&mut self, _slot: u8, _function: i64) {}
+
+    fn fault(&mut self, _fault: &Fault) {}
+}
+
+/// Calls an `Observer` hook if the feature is enabled and an observer is set, the arguments
+/// are not evaluated otherwise.
+macro_rules! observe {
+    ($vm:expr, $hook:ident($($arg:expr),*)) => {
+        #[cfg(feature = "observer")]
+        {
+            if let Some(observer) = $vm.observer.as_deref_mut() { observer.$hook($($arg),*); }
+        }
+    };
+}
+
+pub(crate) use observe;
@@ -29,12 +29,73 @@ pub enum OpCode {
     JPC = 0x0c,    
     JPS = 0x0d,     
 
+    // Extended page, followed by an `ExtOpCode` byte
+    EXT = 0x0e,
+
     RET = 0x0f,
     CLR = 0x1f,    
     INV = 0x2f,
     NEG = 0x3f,
 
+    // Hardware interrupts
+    EI  = 0x4f,
+    DI  = 0x5f,
+    RTI = 0x6f,
+    IVT = 0x7f,
+    WFI = 0x8f,
+
     BRK = 0xdf,
     HLT = 0xef,
     END = 0xff,
+}
+
+/// Operations of the extended page, in the byte following `OpCode::EXT`. `CMP` to `MUL` take
+/// an argument like `add`, `SWP` only a register. `FAR` is followed by a little endian u16
+/// absolute target and jumps or calls like the `CAL`, `JMP`, `JPZ`, `JPC` or `JPS` opcode in
+/// the high nibble of the `EXT` byte, or on a `Cond` there. `JCC` jumps by the atom offset
+/// that follows if the `Cond` in the high nibble holds, `DJN` decrements the register in the
+/// high nibble and jumps by the atom offset unless it reached zero. `LOX` and `STX` address memory at the
+/// register in the high nibble plus the atom that follows, `LOI` and `STI` at the register,
+/// which is incremented afterwards.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum ExtOpCode {
+    CMP = 0x00,
+    AND = 0x01,
+    OR  = 0x02,
+    XOR = 0x03,
+    SHL = 0x04,
+    SHR = 0x05,
+    MUL = 0x06,
+    INC = 0x07,
+    DEC = 0x08,
+    SWP = 0x09,
+    FAR = 0x0a,
+    LOX = 0x0b,
+    STX = 0x0c,
+    LOI = 0x0d,
+    STI = 0x0e,
+    JCC = 0x0f,
+    DJN = 0x10,
+}
+
+/// Relations tested by `ExtOpCode::JCC` and `ExtOpCode::FAR` after a `cmp` or `sub`, signed
+/// ones on sign and overflow, unsigned ones on carry.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum Cond {
+    LT = 0x00,
+    LE = 0x01,
+    GT = 0x02,
+    GE = 0x03,
+    LO = 0x04,
+    LS = 0x05,
+    HI = 0x06,
+    HS = 0x07,
+}
+
+impl ExtOpCode {
+    pub fn has_argument(op: u8) -> bool {
+        op <= ExtOpCode::MUL as u8 || op == ExtOpCode::SWP as u8
+    }
 }
\ No newline at end of file
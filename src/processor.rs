@@ -1,4 +1,4 @@
-use crate::MEM_SIZE;
+use crate::opcodes::Cond;
 
 pub struct Processor {
     pub stack_ptr: usize,
@@ -6,15 +6,62 @@ pub struct Processor {
     pub act_reg: usize,
     pub zero: bool,    
     pub carry: bool,
-    pub sign: bool    
+    pub sign: bool,
+    /// The last `add` or `sub` overflowed as a signed operation, while carry is its unsigned
+    /// carry or borrow.
+    pub overflow: bool,
+    /// Whether raised IRQs are delivered, set by `ei` and cleared by `di` and on delivery.
+    pub irq_enabled: bool
 }
-impl Default for Processor {
-    fn default() -> Self { Self {
-        stack_ptr: (MEM_SIZE - 1),
+
+impl Processor {
+    /// Creates a processor whose stack starts at the top of a memory of `mem_size` atoms.
+    pub fn new(mem_size: usize) -> Self { Self {
+        stack_ptr: mem_size.saturating_sub(1),
         prog_cnt: 0,    
         act_reg: 0,
         zero: false,
         carry: false,
-        sign: false        
+        sign: false,
+        overflow: false,
+        irq_enabled: false
     } }
 }
+
+impl Processor {
+    pub(crate) const ZERO: u8 = 1 << 0;
+    pub(crate) const CARRY: u8 = 1 << 1;
+    pub(crate) const SIGN: u8 = 1 << 2;
+    pub(crate) const OVERFLOW: u8 = 1 << 3;
+    pub(crate) const IRQ_ENABLED: u8 = 1 << 4;
+
+    /// The flags packed into a byte, bit 0 zero, bit 1 carry, bit 2 sign, bit 3 overflow,
+    /// bit 4 IRQs enabled.
+    pub(crate) fn flags(&self) -> u8 {
+        (if self.zero { Self::ZERO } else { 0 }) | (if self.carry { Self::CARRY } else { 0 }) | (if self.sign { Self::SIGN } else { 0 }) | (if self.overflow { Self::OVERFLOW } else { 0 }) | (if self.irq_enabled { Self::IRQ_ENABLED } else { 0 })
+    }
+
+    pub(crate) fn set_flags(&mut self, flags: u8) {
+        self.zero = flags & Self::ZERO != 0;
+        self.carry = flags & Self::CARRY != 0;
+        self.sign = flags & Self::SIGN != 0;
+        self.overflow = flags & Self::OVERFLOW != 0;
+        self.irq_enabled = flags & Self::IRQ_ENABLED != 0;
+    }
+
+    /// Whether the `Cond` holds, `None` for an unknown one.
+    pub(crate) fn condition(&self, cond: u8) -> Option<bool> {
+        let lt = self.sign != self.overflow;
+        match cond {
+            x if x == (Cond::LT as u8) => Some(lt),
+            x if x == (Cond::LE as u8) => Some(lt || self.zero),
+            x if x == (Cond::GT as u8) => Some(!lt && !self.zero),
+            x if x == (Cond::GE as u8) => Some(!lt),
+            x if x == (Cond::LO as u8) => Some(self.carry),
+            x if x == (Cond::LS as u8) => Some(self.carry || self.zero),
+            x if x == (Cond::HI as u8) => Some(!self.carry && !self.zero),
+            x if x == (Cond::HS as u8) => Some(!self.carry),
+            _ => None
+        }
+    }
+}
@@ -1,10 +1,83 @@
-pub struct Program <'a> {
+use core::marker::PhantomData;
+use cfg_block::cfg_block;
+
+use crate::{Atom, VMAtom, binary::{Header, Imports, HEADER_SIZE}};
+
+/// A compiled binary borrowed from its owner, e.g. a `ProgramBuf` or a slice in flash.
+pub struct Program <'a, A: Atom = VMAtom> {
     pub source: u8,
     pub id: &'a str,
-    pub data: &'a [u8],    
+    /// Private, as code and import table are split from it once so stepping does not
+    /// parse the header.
+    data: &'a [u8],    
+    code: &'a [u8],
+    table: &'a [u8],
+    entry: usize,
+    atom: PhantomData<A>
 }
 
-impl Program <'_> {
-    pub const EMPTY: Program <'static> = Program { source: 255, id: "-empty-", data: &[] };
-    pub const ERROR: Program <'static> = Program { source: 255, id: "-error-", data: &[] };
-}
\ No newline at end of file
+impl <A: Atom> Clone for Program <'_, A> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl <A: Atom> Copy for Program <'_, A> {}
+
+impl <'a, A: Atom> Program <'a, A> {
+    pub const EMPTY: Program <'static, A> = Program { source: 255, id: "-empty-", data: &[], code: &[], table: &[], entry: 0, atom: PhantomData };
+    pub const ERROR: Program <'static, A> = Program { source: 255, id: "-error-", data: &[], code: &[], table: &[], entry: 0, atom: PhantomData };
+
+    pub fn new(source: u8, id: &'a str, data: &'a [u8]) -> Self {
+        let header = Header::parse(data);
+        let code_end = header.and_then(|header| HEADER_SIZE.checked_add(header.code_len)).filter(|end| *end <= data.len());
+        let (code, table) = match code_end {
+            Some(end) => (&data[HEADER_SIZE .. end], &data[end ..]),
+            None => (&[][..], &[][..])
+        };
+        Program { source, id, data, code, table, entry: header.map(|header| header.entry).unwrap_or(0), atom: PhantomData }
+    }
+
+    /// The whole binary, header included.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The executable part of the binary, empty if the header is missing or the code truncated.
+    pub fn code(&self) -> &'a [u8] {
+        self.code
+    }
+
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// The names of the interrupts the program calls, indexed by the `int` slot.
+    pub fn imports(&self) -> Imports<'a> {
+        Imports::table(self.table)
+    }
+}
+
+cfg_block! {
+    #[cfg(feature = "alloc")] {
+        extern crate alloc;
+
+        use alloc::{string::String, vec::Vec};
+
+        /// A compiled binary owning its name and data, as returned by `VirtMach::compile`.
+        pub struct ProgramBuf <A: Atom = VMAtom> {
+            pub source: u8,
+            pub id: String,
+            pub data: Vec<u8>,
+            atom: PhantomData<A>
+        }
+
+        impl <A: Atom> ProgramBuf <A> {
+            pub fn new(source: u8, id: String, data: Vec<u8>) -> Self {
+                ProgramBuf { source, id, data, atom: PhantomData }
+            }
+
+            pub fn as_program(&self) -> Program<'_, A> {
+                Program::new(self.source, &self.id, &self.data)
+            }
+        }
+    }
+}
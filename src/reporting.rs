@@ -1,13 +1,16 @@
 use core::fmt::Write;
-use crate::{RuntimeError, VirtMach, Writer, MEM_SIZE, REG_MAX};
+use crate::{Atom, RuntimeError, VirtMach, Writer};
 
-impl VirtMach <'_> {
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
     pub fn log(&self) {
-        if self.program.data.len() == 0 {
+        if let Some(fault) = &self.fault {
+            log::error!("[{:5?}] {}", self.state, fault);
+        }
+        if self.program.code().len() == 0 {
             log::error!("[{:5?}] no program loaded", self.state);
             return;
         }
-        if self.processor.prog_cnt > self.program.data.len() {
+        if self.processor.prog_cnt > self.program.code().len() {
             log::error!("[{:5?}] program out of bounds", self.state);
             return;
         }
@@ -20,7 +23,7 @@ impl VirtMach <'_> {
     }
 }
 
-impl VirtMach <'_> {
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
     pub fn write_status<W: Write>(&self, mut writer: W) {                
         let _ = writer.write_fmt(format_args!("{:12}: {:?}-{}-{:05}-{:05}", self.program.id, self.state, self.error.clone() as u8, self.processor.prog_cnt, self.processor.stack_ptr));
     }
@@ -40,9 +43,9 @@ impl VirtMach <'_> {
             }else{
                 let _ = writer.write_fmt(format_args!("-{:24.24?}-|\n", self.error));
             }
-            let _ = writer.write_fmt(format_args!("{:7.7}|   {:04x}|", &self.program.id, self.processor.prog_cnt));
+            let _ = writer.write_fmt(format_args!("{:7.7}|{:?}{:04x}|", &self.program.id, self.state, self.processor.prog_cnt));
             if columns == 1 { let _ = writer.write_str("\n"); }
-            let _ = writer.write_fmt(format_args!("STA@FLG|{:?} {}{}{}|", self.state, if self.processor.zero { 'Z' } else { '.' }, if self.processor.carry { 'C' } else { '.' }, if self.processor.sign { 'S' } else { '.' }));            
+            let _ = writer.write_fmt(format_args!("FLAGS@@|  {}{}{}{}{}|", if self.processor.zero { 'Z' } else { '.' }, if self.processor.carry { 'C' } else { '.' }, if self.processor.sign { 'S' } else { '.' }, if self.processor.overflow { 'V' } else { '.' }, if self.processor.irq_enabled { 'I' } else { '.' }));            
             let _ = writer.write_str("\n");
             let _ = writer.write_fmt(format_args!("STCK@RG|{:4}  {:X}|", self.processor.stack_ptr, self.processor.act_reg));            
             if columns == 1 { let _ = writer.write_str("\n"); }
@@ -52,7 +55,7 @@ impl VirtMach <'_> {
         if mask & (1 << 1) != 0 {
             write_line(&mut writer);
             let _ = writer.write_fmt(format_args!("REGS@@@|"));
-            for i in 0 .. REG_MAX {                        
+            for i in 0 .. REGS {                        
                 if i % (columns * 2) == (columns * 2) - 1 { let _ = writer.write_str("\n"); }
                 let _ = writer.write_fmt(format_args!("{:7}|", self.registers[i]));            
             }        
@@ -61,7 +64,7 @@ impl VirtMach <'_> {
         if mask & (1 << 2) != 0 {
             write_line(&mut writer);
             let _ = writer.write_fmt(format_args!("MEMORY@|"));
-            for i in 0 .. MEM_SIZE {                        
+            for i in 0 .. self.memory.len() {                        
                 if i % (columns * 2) == (columns * 2) - 1 { let _ = writer.write_str("\n"); }
                 let _ = writer.write_fmt(format_args!("{:7}|", self.memory[i]));            
             }        
@@ -80,7 +83,7 @@ impl VirtMach <'_> {
                         let _ = writer.write_str("\n");
                     }
                     _ => {
-                        if pos < self.program.data.len() {
+                        if pos < self.program.code().len() {
                             let _ = writer.write_fmt(format_args!("{}{:4}:", if i == 1 { ">" } else { " " }, pos));                    
                             let mut buf = [0u8;16];
                             let mut op = Writer::from_buffer(&mut buf);
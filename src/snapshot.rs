@@ -0,0 +1,115 @@
+//! `VirtMach` snapshots for checkpointing. Layout, all numbers little endian:
+//!
+//! | offset | size | content                                                 |
+//! |--------|------|---------------------------------------------------------|
+//! | 0      | 4    | magic `VMSS`                                            |
+//! | 4      | 1    | `SNAPSHOT_VERSION`                                      |
+//! | 5      | 1    | atom type id                                            |
+//! | 6      | 1    | register count                                          |
+//! | 7      | 1    | state                                                   |
+//! | 8      | 1    | flags, bit 0 zero, 1 carry, 2 sign, 3 overflow, 4 IRQs  |
+//! | 9      | 1    | active register                                         |
+//! | 10     | 4    | checksum of the loaded program                          |
+//! | 14     | 4    | program counter                                         |
+//! | 18     | 4    | stack pointer                                           |
+//! | 22     | 8    | cycle count                                             |
+//! | 30     | 4    | memory size in atoms                                    |
+//! | 34     | 1    | pending IRQ lines                                       |
+//! | 35     | 16   | IRQ handler positions, `u32::MAX` if unset              |
+//! | 51     | 8    | `wfi` deadline, `u64::MAX` if none                      |
+//! | 59     |      | registers, then memory                                  |
+
+use crate::{Atom, Runtime, RuntimeError, VirtMach, IRQ_MAX, binary::Header};
+
+const MAGIC: [u8;4] = *b"VMSS";
+/// Version of the snapshot encoding, bumped whenever its layout changes.
+pub const SNAPSHOT_VERSION: u8 = 4;
+/// Bytes preceding the registers and memory in a snapshot.
+const HEADER_SIZE: usize = 59;
+
+impl <A: Atom, const REGS: usize> VirtMach <'_, A, REGS> {
+    /// Bytes needed by `snapshot`.
+    pub fn snapshot_size(&self) -> usize {
+        HEADER_SIZE + (REGS + self.memory.len()) * A::SIZE
+    }
+
+    /// Writes registers, memory, processor state, cycle count and state to `buf` and returns
+    /// the number of bytes written. A faulted VM is not snapshotted, its error is returned.
+    pub fn snapshot(&self, buf: &mut [u8]) -> Result<usize, RuntimeError> {
+        let size = self.snapshot_size();
+        if self.state == Runtime::Err { return Err(self.error.clone()); }
+        if buf.len() < size { return Err(RuntimeError::BufferTooSmall); }
+        let (Ok(pc), Ok(sp), Ok(mem_len)) = (u32::try_from(self.processor.prog_cnt), u32::try_from(self.processor.stack_ptr), u32::try_from(self.memory.len())) else {
+            return Err(RuntimeError::MalformedSnapshot);
+        };
+
+        buf[0..4].copy_from_slice(&MAGIC);
+        buf[4] = SNAPSHOT_VERSION;
+        buf[5] = A::ATOM_ID;
+        buf[6] = REGS as u8;
+        buf[7] = match self.state { Runtime::Ini => 0, Runtime::Run => 1, Runtime::Hlt => 2, Runtime::Wfi => 4, _ => 3 };
+        buf[8] = self.processor.flags();
+        buf[9] = self.processor.act_reg as u8;
+        buf[10..14].copy_from_slice(&self.program_checksum().to_le_bytes());
+        buf[14..18].copy_from_slice(&pc.to_le_bytes());
+        buf[18..22].copy_from_slice(&sp.to_le_bytes());
+        buf[22..30].copy_from_slice(&(self.cycle_cnt as u64).to_le_bytes());
+        buf[30..34].copy_from_slice(&mem_len.to_le_bytes());
+        buf[34] = self.irq.pending;
+        for (i, vector) in self.irq.vectors.iter().enumerate() {
+            let vector = vector.and_then(|v| u32::try_from(v).ok()).unwrap_or(u32::MAX);
+            buf[35 + i * 4..39 + i * 4].copy_from_slice(&vector.to_le_bytes());
+        }
+        buf[51..59].copy_from_slice(&self.irq.deadline.unwrap_or(u64::MAX).to_le_bytes());
+        for (i, a) in self.registers.iter().chain(self.memory.iter()).enumerate() {
+            a.to_le_bytes(&mut buf[HEADER_SIZE + i * A::SIZE..]);
+        }
+        return Ok(size);
+    }
+
+    /// Restores a `snapshot` taken of a VM with the same atom type, register count, memory
+    /// size and loaded program. Nothing is changed if the snapshot is rejected.
+    pub fn restore(&mut self, buf: &[u8]) -> Result<(), RuntimeError> {
+        if buf.len() < HEADER_SIZE || buf[0..4] != MAGIC { return Err(RuntimeError::MalformedSnapshot); }
+        if buf[4] != SNAPSHOT_VERSION { return Err(RuntimeError::UnsupportedVersion); }
+        if buf[5] != A::ATOM_ID { return Err(RuntimeError::MismatchedAtomType); }
+        if u32::from_le_bytes(buf[10..14].try_into().unwrap()) != self.program_checksum() { return Err(RuntimeError::MismatchedProgram); }
+
+        let pc = u32::from_le_bytes(buf[14..18].try_into().unwrap()) as usize;
+        let sp = u32::from_le_bytes(buf[18..22].try_into().unwrap()) as usize;
+        let mem_len = u32::from_le_bytes(buf[30..34].try_into().unwrap()) as usize;
+        let act_reg = buf[9] as usize;
+        let state = match buf[7] { 0 => Runtime::Ini, 1 => Runtime::Run, 2 => Runtime::Hlt, 3 => Runtime::Stp, 4 => Runtime::Wfi, _ => return Err(RuntimeError::MalformedSnapshot) };
+        if buf[6] as usize != REGS || mem_len != self.memory.len() || buf.len() != self.snapshot_size() { return Err(RuntimeError::MalformedSnapshot); }
+        if act_reg >= REGS || pc > self.program.code().len() || (sp >= mem_len && mem_len > 0) { return Err(RuntimeError::MalformedSnapshot); }
+        // pending lines and handlers are used as indices and jump targets without further checks
+        let vectors = (0 .. IRQ_MAX).map(|i| u32::from_le_bytes(buf[35 + i * 4..39 + i * 4].try_into().unwrap()));
+        if buf[34] >> IRQ_MAX != 0 || vectors.clone().any(|v| v != u32::MAX && v as usize >= self.program.code().len()) { return Err(RuntimeError::MalformedSnapshot); }
+
+        let mut atoms = buf[HEADER_SIZE..].chunks_exact(A::SIZE).map(A::from_le_bytes);
+        for (reg, a) in self.registers.iter_mut().zip(&mut atoms) { *reg = a; }
+        for (cell, a) in self.memory.iter_mut().zip(&mut atoms) { *cell = a; }
+        self.processor.prog_cnt = pc;
+        self.processor.stack_ptr = sp;
+        self.processor.act_reg = act_reg;
+        self.processor.set_flags(buf[8]);
+        self.irq.pending = buf[34];
+        for (vector, v) in self.irq.vectors.iter_mut().zip(vectors) {
+            *vector = if v == u32::MAX { None } else { Some(v as usize) };
+        }
+        let deadline = u64::from_le_bytes(buf[51..59].try_into().unwrap());
+        self.irq.deadline = if deadline == u64::MAX { None } else { Some(deadline) };
+        self.cycle_cnt = u64::from_le_bytes(buf[22..30].try_into().unwrap()) as usize;
+        self.state = state;
+        self.error = RuntimeError::NoError;
+        self.fault = None;
+        self.debug.resume = false;
+        self.debug.hit = None;
+        self.history.clear();
+        return Ok(());
+    }
+
+    fn program_checksum(&self) -> u32 {
+        Header::parse(self.program.data()).map(|header| header.checksum).unwrap_or(0)
+    }
+}
@@ -1,16 +1,25 @@
-use core::ops::Neg;
-use core::{slice, str};
-
-use crate::{REG_MAX, MEM_SIZE};
-use crate::opcodes::OpCode;
+use crate::{REG_MAX, IRQ_MAX};
+use crate::opcodes::{OpCode, ExtOpCode};
 use crate::processor::Processor;
+use crate::binary;
+use crate::debug::{Debugger, WatchTarget};
+use crate::history::History;
+use crate::irq::Irq;
+use crate::observer::observe;
+#[cfg(feature = "observer")]
+use crate::observer::Observer;
 
-pub use crate::atom::{ATOM_ID, VMAtom, VMAddr, VAtom};
+pub use crate::atom::{Atom, VMAtom, VAtom};
 pub use crate::errors::RuntimeError as RuntimeError;
+pub use crate::errors::Fault as Fault;
 pub use crate::program::Program as Program;
+#[cfg(feature = "alloc")]
+pub use crate::program::ProgramBuf as ProgramBuf;
 pub use crate::writer::Writer as Writer;
 use crate::interrupts;
 
+const UNBOUND: u8 = u8::MAX;
+
 #[derive(Debug)]
 #[derive(PartialEq)]
 pub enum Runtime {
@@ -18,169 +27,440 @@ pub enum Runtime {
     Run,
     Hlt,
     Stp,    
-    Err
+    Err,
+    /// Waiting for an interrupt after `wfi`.
+    Wfi
+}
+
+/// Why `run` or `step` returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// The instruction budget is used up, the VM is still running.
+    BudgetExhausted,
+    /// `hlt` was executed or the VM was paused, `run` continues.
+    Halted,
+    /// `end` was executed or no program is loaded, the VM needs a reset or new program.
+    Ended,
+    /// A host breakpoint was reached or `brk` executed with halting on breaks enabled, at
+    /// the given program position.
+    Breakpoint(usize),
+    /// The instruction at the given program position (second) triggered the watchpoint with
+    /// the given id (first).
+    Watchpoint(usize, usize),
+    /// The instruction at the given program position faulted.
+    Fault(RuntimeError, usize),
+    /// The program waits for an interrupt request.
+    WaitingForInterrupt
 }
-pub struct VirtMach <'a> {
-    pub registers: [VMAtom;REG_MAX],
-    pub memory: [VMAtom;MEM_SIZE],
+/// A virtual machine on atoms of type `A` with a register file of `REGS` registers (at most [`REG_MAX`]).
+pub struct VirtMach <'a, A: Atom = VMAtom, const REGS: usize = REG_MAX> {
+    pub registers: [A;REGS],
+    pub memory: &'a mut [A],
     pub cycle_cnt: usize,
-    pub(crate) program: Program<'a>,    
+    pub(crate) program: Program<'a, A>,    
     pub error: RuntimeError,    
+    pub fault: Option<Fault>,
     pub(crate) processor: Processor,
     pub state: Runtime,
-    halt_on_break: bool
+    halt_on_break: bool,
+    bindings: [u8;binary::IMPORT_MAX],
+    pub(crate) operand: Option<i64>,
+    pub(crate) debug: Debugger<A>,
+    pub(crate) history: History<'a, A>,
+    pub(crate) irq: Irq,
+    #[cfg(feature = "observer")]
+    pub(crate) observer: Option<&'a mut dyn Observer<A>>
 }
 
-impl <'a> VirtMach <'_> {
-    pub fn new() -> Self {        
+impl <'a, A: Atom, const REGS: usize> VirtMach <'a, A, REGS> {
+    /// Creates a VM working on the caller-supplied `memory`, which holds both the
+    /// addressable cells and the stack growing down from its end.
+    pub fn new(memory: &'a mut [A]) -> Self {        
+        const { assert!(REGS <= REG_MAX, "register file exceeds the addressable register count") };
+
+        let mem_size = memory.len();
         let res =  Self {
-            registers: [0 as VMAtom;REG_MAX],
-            memory: [0 as VMAtom;MEM_SIZE],            
+            registers: [A::ZERO;REGS],
+            memory,            
             program: Program::EMPTY,                
             error: RuntimeError::NoError,
+            fault: None,
             cycle_cnt: 0,
-            processor: Processor::default(),
+            processor: Processor::new(mem_size),
             state: Runtime::Ini,
-            halt_on_break: false
+            halt_on_break: false,
+            bindings: [UNBOUND;binary::IMPORT_MAX],
+            operand: None,
+            debug: Debugger::new(),
+            history: History::new(&mut []),
+            irq: Irq::new(),
+            #[cfg(feature = "observer")]
+            observer: None
         };    
         
         return res;  
     }
 
-    pub fn load_program (&mut self, program: Program) {        
-        if program.data.len() == 0 {
+    /// Validates and loads `program`, which has to outlive the VM, binds its imports against
+    /// `interrupts` and resets the processor.
+    pub fn load_program (&mut self, program: Program<'a, A>, interrupts: &[&mut dyn interrupts::SoftInterrupt<A, REGS>]) -> Result<(), RuntimeError> {        
+        if program.data().len() == 0 {
             self.program = Program::EMPTY;
-            return;
+            self.bindings = [UNBOUND;binary::IMPORT_MAX];
+            return Ok(());
         }
 
-        let data = &program.data[1..];
-        
-        if data.len() == 0 {
-            self.program = Program::EMPTY;
-            return;
+        let header = binary::validate::<A>(program.data()).and_then(|header| if header.regs as usize > REGS { Err(RuntimeError::RegisterOutOfBounds) } else { Ok(header) });
+        if let Err(err) = header {
+            self.program = Program::ERROR;
+            self.error = err.clone();
+            self.fault = Some(Fault { error: err.clone(), pc: 0, opcode: None, operand: None, interrupt: None });
+            self.state = Runtime::Err;
+            return Err(err);
         }
 
-        if program.data[0] != ATOM_ID {
-            self.program = Program::ERROR;
-            self.error = RuntimeError::MismatchedAtomType;
-            return;
+        if program.code().len() == 0 {
+            self.program = Program::EMPTY;
+            self.bindings = [UNBOUND;binary::IMPORT_MAX];
+            return Ok(());
         }
     
+        self.program = program;
         self.reset();  
-        self.program.source = program.source;
-        self.program.id = unsafe { str::from_utf8_unchecked(slice::from_raw_parts(program.id.as_ptr(), program.id.len())) };
-        self.program.data = unsafe { slice::from_raw_parts(data.as_ptr(), data.len()) };
-        self.processor = Processor::default();         
-        self.state = Runtime::Hlt;             
+        return self.bind(interrupts);
+    }
+
+    /// Maps each slot of the program's import table to the interrupt in `interrupts` whose
+    /// `name()` matches, failing with `MissingInterrupt` if one is not supplied.
+    pub fn bind (&mut self, interrupts: &[&mut dyn interrupts::SoftInterrupt<A, REGS>]) -> Result<(), RuntimeError> {
+        self.bindings = [UNBOUND;binary::IMPORT_MAX];
+        for (slot, name) in self.program.imports().enumerate() {
+            match interrupts.iter().position(|int| int.name() == name) {
+                Some(i) if i < UNBOUND as usize => { self.bindings[slot] = i as u8; }
+                _ => {
+                    self.error = RuntimeError::MissingInterrupt;
+                    self.fault = Some(Fault { error: RuntimeError::MissingInterrupt, pc: self.processor.prog_cnt, opcode: None, operand: None, interrupt: Some((slot as u8, 0)) });
+                    self.state = Runtime::Err;
+                    return Err(RuntimeError::MissingInterrupt);
+                }
+            }
+        }
+        return Ok(());
     }
 
-    pub fn stack_push(&mut self, val: VMAtom) {
+    pub fn stack_push(&mut self, val: A) {
         if self.processor.stack_ptr == 0 {
             self.error = RuntimeError::HeapOverflow;
+            self.operand = Some(self.processor.stack_ptr as i64);
         } else {
+            self.debug.access(WatchTarget::Memory(self.processor.stack_ptr), Some(val));
+            observe!(self, stack_push(self.processor.stack_ptr, val));
+            self.history.memory(self.processor.stack_ptr, self.memory[self.processor.stack_ptr]);
             self.memory[self.processor.stack_ptr] = val;
             self.processor.stack_ptr -= 1;
         }
     }
 
-    pub fn stack_pop(&mut self) ->  VMAtom {
-        if self.processor.stack_ptr >= MEM_SIZE - 1 {
+    pub fn stack_pop(&mut self) ->  A {
+        if self.processor.stack_ptr + 1 >= self.memory.len() {
             self.error = RuntimeError::HeapUnderflow;
-            return 0 as VMAtom;
+            self.operand = Some(self.processor.stack_ptr as i64);
+            return A::ZERO;
         } else {
             self.processor.stack_ptr += 1;
-            return self.memory[self.processor.stack_ptr];            
+            let val = self.mem_read(self.processor.stack_ptr);
+            observe!(self, stack_pop(self.processor.stack_ptr, val));
+            return val;            
         }
     }
 
-    pub fn step (&mut self, interrupts: &mut [&'_ mut dyn interrupts::SoftInterrupt]) {
+    pub(crate) fn mem_read(&mut self, addr: usize) -> A {
+        self.debug.access(WatchTarget::Memory(addr), None);
+        return self.memory[addr];
+    }
+
+    pub(crate) fn mem_write(&mut self, addr: usize, val: A) {
+        self.debug.access(WatchTarget::Memory(addr), Some(val));
+        observe!(self, memory_write(addr, self.memory[addr], val));
+        self.history.memory(addr, self.memory[addr]);
+        self.memory[addr] = val;
+    }
+
+    fn reg_read(&mut self, reg: usize) -> A {
+        self.debug.access(WatchTarget::Register(reg), None);
+        return self.registers[reg];
+    }
+
+    fn reg_write(&mut self, reg: usize, val: A) {
+        self.debug.access(WatchTarget::Register(reg), Some(val));
+        self.history.register(reg, self.registers[reg]);
+        self.registers[reg] = val;
+    }
+
+    /// The value on top of the stack, without popping it.
+    pub fn stack_peek(&self) -> A {
+        return self.memory.get(self.processor.stack_ptr + 1).copied().unwrap_or(A::ZERO);
+    }
+
+    /// The number of values on the stack.
+    pub fn stack_depth(&self) -> usize {
+        return self.memory.len().saturating_sub(self.processor.stack_ptr + 1);
+    }
+
+    /// Executes a single instruction, returning `BudgetExhausted` if the VM keeps running.
+    pub fn step (&mut self, interrupts: &mut [&'_ mut dyn interrupts::SoftInterrupt<A, REGS>]) -> StopReason {
+        self.wake_on_irq();
         if self.state != Runtime::Run {
-            return;
+            return self.stop_reason();
         }
 
-        if self.processor.prog_cnt >= self.program.data.len() {
+        let instructions = self.program.code();        
+
+        self.operand = None;
+
+        if self.processor.prog_cnt >= instructions.len() {
             self.error = RuntimeError::ProgramOutOfBounds;
-            return;
+            self.fault = Some(Fault { error: RuntimeError::ProgramOutOfBounds, pc: self.processor.prog_cnt, opcode: None, operand: None, interrupt: None });
+            observe!(self, fault(self.fault.as_ref().unwrap()));
+            self.state = Runtime::Err;
+            return self.stop_reason();
         }
 
-        let instructions = self.program.data;        
+        if !self.debug.resume && self.debug.breakpoint(self.processor.prog_cnt) {
+            self.debug.resume = true;
+            self.state = Runtime::Hlt;
+            return StopReason::Breakpoint(self.processor.prog_cnt);
+        }
+        self.debug.resume = false;
+        self.debug.hit = None;
+        self.history.step(self.processor.prog_cnt, self.processor.stack_ptr, self.processor.act_reg, self.processor.flags(), self.irq.pending);
 
         let byte = instructions[self.processor.prog_cnt];
+        observe!(self, before_instruction(self.processor.prog_cnt, byte));
         let op = byte & 0x0f;
         let reg:u8;
         let inst_pos = self.processor.prog_cnt;
         self.processor.prog_cnt += 1;
 
-        let val: VMAtom;
-        if op < 0x0f {
+        let mut ext = 0u8;
+        let mut far = 0usize;
+        let mut offset = A::ZERO;
+        if op == OpCode::EXT as u8 {
+            match instructions.get(self.processor.prog_cnt) {
+                Some(byte) => { ext = *byte; }
+                None => { self.error = RuntimeError::ProgramOutOfBounds; self.operand = Some(self.processor.prog_cnt as i64); }
+            }
+            self.processor.prog_cnt += 1;
+            if ext == ExtOpCode::FAR as u8 {
+                match instructions.get(self.processor.prog_cnt .. self.processor.prog_cnt + 2) {
+                    Some(bytes) => { far = u16::from_le_bytes([bytes[0], bytes[1]]) as usize; }
+                    None => { self.error = RuntimeError::ProgramOutOfBounds; self.operand = Some(self.processor.prog_cnt as i64); }
+                }
+                self.processor.prog_cnt += 2;
+            }
+            if ext == ExtOpCode::LOX as u8 || ext == ExtOpCode::STX as u8 || ext == ExtOpCode::JCC as u8 || ext == ExtOpCode::DJN as u8 {
+                if self.processor.prog_cnt + A::SIZE <= instructions.len() {
+                    offset = instructions[self.processor.prog_cnt ..].as_ref().get_atom();
+                }else{
+                    self.error = RuntimeError::ProgramOutOfBounds;
+                    self.operand = Some(self.processor.prog_cnt as i64);
+                }
+                self.processor.prog_cnt += A::SIZE;
+            }
+        }
+
+        let val: A;
+        if op < 0x0f && (op != OpCode::EXT as u8 || ExtOpCode::has_argument(ext)) {
             reg = (byte >> 4) & 0x0f;
             if reg == 15 {
-                val = instructions[self.processor.prog_cnt .. self.processor.prog_cnt + size_of::<VMAtom>()].as_ref().get_atom();
-                self.processor.prog_cnt += 2;
+                if self.processor.prog_cnt + A::SIZE <= instructions.len() {
+                    val = instructions[self.processor.prog_cnt ..].as_ref().get_atom();
+                }else{
+                    self.error = RuntimeError::ProgramOutOfBounds;
+                    self.operand = Some(self.processor.prog_cnt as i64);
+                    val = A::ZERO;
+                }
+                self.processor.prog_cnt += A::SIZE;
+            }else if op == OpCode::INT as u8 {
+                // the nibble is the import slot, not a register
+                val = A::ZERO;
+            }else if (reg as usize) < REGS {
+                // `reg` and `pop` name a register without reading it
+                val = if op == OpCode::REG as u8 || op == OpCode::POP as u8 { A::ZERO } else { self.reg_read(reg as usize) };
             }else{
-                val  = self.registers[reg as usize];
+                self.error = RuntimeError::RegisterOutOfBounds; self.operand = Some(reg as i64);
+                val = A::ZERO;
             }
         }else{
             reg = 0xf;
-            val = 0 as VMAtom;
+            val = A::ZERO;
         }
 
-        fn add(vm: &mut VirtMach, a: VMAtom, b: VMAtom) -> VMAtom {            
-            let add_res = a.overflowing_add(b);
-            vm.processor.zero = add_res.0 == 0;                        
-            vm.processor.sign = add_res.0 < 0;
+        fn add<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, a: A, b: A) -> A {            
+            let add_res = a.add_with_flags(b);
+            vm.processor.zero = add_res.0 == A::ZERO;                        
+            vm.processor.sign = add_res.0.sign_bit();
             vm.processor.carry = add_res.1;
+            vm.processor.overflow = add_res.2;
             return add_res.0;
         }
 
-        fn sub(vm: &mut VirtMach, a: VMAtom, b: VMAtom) -> VMAtom {            
-            let sub_res = a.overflowing_sub(b);
-            vm.processor.zero = sub_res.0 == 0;                        
-            vm.processor.sign = sub_res.0 < 0;
+        fn sub<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, a: A, b: A) -> A {            
+            let sub_res = a.sub_with_flags(b);
+            vm.processor.zero = sub_res.0 == A::ZERO;                        
+            vm.processor.sign = sub_res.0.sign_bit();
             vm.processor.carry = sub_res.1;
+            vm.processor.overflow = sub_res.2;
             return sub_res.0;
         }
 
-        fn jmpchk(vm: &mut VirtMach, offset: VMAtom, is_cal: bool) {            
-            match VMAddr::try_from(vm.processor.prog_cnt) {
-                Ok(prog_cnt) => {
-                    let res = prog_cnt.overflowing_add(offset as VMAddr);
-                    if res.1 == false && res.0 >= 0 {
-                        if is_cal { vm.stack_push(vm.processor.prog_cnt as VMAtom); }
-                        vm.processor.prog_cnt = res.0 as usize;
-                    }else{
-                        vm.error = RuntimeError::InstructionPointerOutOfBounds
-                    }            
-                }
-                Err(_) => vm.error = RuntimeError::InstructionPointerOutOfBounds
+        fn logic<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, res: A, carry: bool) -> A {
+            vm.processor.zero = res == A::ZERO;
+            vm.processor.sign = res.sign_bit();
+            vm.processor.carry = carry;
+            vm.processor.overflow = carry;
+            return res;
+        }
+
+        fn jmpchk<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, offset: A, is_cal: bool) {            
+            let target = vm.processor.prog_cnt as i64 + offset.to_offset();
+            match usize::try_from(target) {
+                Ok(prog_cnt) => { jmpabs(vm, prog_cnt, is_cal); }
+                Err(_) => { vm.error = RuntimeError::InstructionPointerOutOfBounds; vm.operand = Some(target); }
             }
             
         }
 
-        fn memchk(vm: &mut VirtMach, addr: VMAtom) -> bool {
-            if addr < 0 as VMAtom || addr >= MEM_SIZE as VMAtom { vm.error = RuntimeError::MemoryOutOfBounds; return false; }
-            if addr >= vm.processor.stack_ptr as VMAtom { vm.error = RuntimeError::HeapCrash; }
-            return true;
+        fn jmpabs<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, prog_cnt: usize, is_cal: bool) {
+            if is_cal {
+                match A::from_usize(vm.processor.prog_cnt) {
+                    Some(ret) => vm.stack_push(ret),
+                    None => { vm.error = RuntimeError::InstructionPointerOutOfBounds; vm.operand = Some(vm.processor.prog_cnt as i64); return; }
+                }
+            }
+            vm.processor.prog_cnt = prog_cnt;
+        }
+
+        fn memchk<A: Atom, const REGS: usize>(vm: &mut VirtMach<'_, A, REGS>, addr: A) -> Option<usize> {
+            match addr.to_usize() {
+                Some(addr) if addr < vm.memory.len() => {
+                    if addr >= vm.processor.stack_ptr { vm.error = RuntimeError::HeapCrash; vm.operand = Some(addr as i64); }
+                    Some(addr)
+                }
+                _ => { vm.error = RuntimeError::MemoryOutOfBounds; vm.operand = Some(addr.to_i64()); None }
+            }
         }
 
+        let mut interrupt = None;
+
         match op {            
-            x if x == (OpCode::REG as u8) => { self.processor.act_reg = reg.into(); }            
-            x if x == (OpCode::SET as u8) => { self.registers[self.processor.act_reg] = val; }            
-            x if x == (OpCode::LOA as u8) => { if memchk(self, val) { self.registers[self.processor.act_reg] = self.memory[val as usize]; } }
-            x if x == (OpCode::STO as u8) => { if memchk(self, val) { self.memory[val as usize] = self.registers[self.processor.act_reg]; } }            
+            _ if self.error != RuntimeError::NoError => {}
+            x if x == (OpCode::REG as u8) => { if (reg as usize) < REGS { self.processor.act_reg = reg.into(); } else { self.error = RuntimeError::RegisterOutOfBounds; self.operand = Some(reg as i64); } }            
+            x if x == (OpCode::SET as u8) => { self.reg_write(self.processor.act_reg, val); }            
+            x if x == (OpCode::LOA as u8) => { if let Some(addr) = memchk(self, val) { let a = self.mem_read(addr); self.reg_write(self.processor.act_reg, a); } }
+            x if x == (OpCode::STO as u8) => { if let Some(addr) = memchk(self, val) { let a = self.reg_read(self.processor.act_reg); self.mem_write(addr, a); } }            
             x if x == (OpCode::PSH as u8) => { self.stack_push(val); }            
-            x if x == (OpCode::POP as u8) => { if reg != 0x0f { self.registers[reg as usize] = self.stack_pop(); } else if memchk(self, val) { self.memory[val as usize] = self.stack_pop(); } else { self.error = RuntimeError::RegisterOutOfBounds; } }                                    
-            x if x == (OpCode::ADD as u8) => { self.registers[self.processor.act_reg] = add(self, self.registers[self.processor.act_reg], val); }                                    
-            x if x == (OpCode::SUB as u8) => { self.registers[self.processor.act_reg] = sub(self, self.registers[self.processor.act_reg], val); }                                   
+            x if x == (OpCode::POP as u8) => { if reg != 0x0f { let a = self.stack_pop(); self.reg_write(reg as usize, a); } else if let Some(addr) = memchk(self, val) { let a = self.stack_pop(); self.mem_write(addr, a); } }                                    
+            x if x == (OpCode::ADD as u8) => { let a = self.reg_read(self.processor.act_reg); let a = add(self, a, val); self.reg_write(self.processor.act_reg, a); }                                    
+            x if x == (OpCode::SUB as u8) => { let a = self.reg_read(self.processor.act_reg); let a = sub(self, a, val); self.reg_write(self.processor.act_reg, a); }                                   
             x if x == (OpCode::CAL as u8) => { jmpchk(self, val, true); }
             x if x == (OpCode::JMP as u8) => { jmpchk(self, val, false); }
             x if x == (OpCode::JPC as u8) => { if self.processor.carry { jmpchk(self, val, false); } }
             x if x == (OpCode::JPZ as u8) => { if self.processor.zero { jmpchk(self, val, false); } }
             x if x == (OpCode::JPS as u8) => { if self.processor.sign { jmpchk(self, val, false); } }                      
+            x if x == (OpCode::EXT as u8) => {
+                // only the arithmetic and logic ops up to `swp` work on the active register
+                let a = if ext <= ExtOpCode::SWP as u8 { self.reg_read(self.processor.act_reg) } else { A::ZERO };
+                let shift = val.to_i64();
+                let res = match ext {
+                    x if x == (ExtOpCode::CMP as u8) => { sub(self, a, val); None }
+                    x if x == (ExtOpCode::AND as u8) => { Some(logic(self, a & val, false)) }
+                    x if x == (ExtOpCode::OR as u8) => { Some(logic(self, a | val, false)) }
+                    x if x == (ExtOpCode::XOR as u8) => { Some(logic(self, a ^ val, false)) }
+                    x if x == (ExtOpCode::SHL as u8) => { Some(logic(self, if (0..A::BITS as i64).contains(&shift) { a << (shift as u32) } else { A::ZERO }, false)) }
+                    x if x == (ExtOpCode::SHR as u8) => { Some(logic(self, if (0..A::BITS as i64).contains(&shift) { a >> (shift as u32) } else if A::SIGNED && a.sign_bit() { !A::ZERO } else { A::ZERO }, false)) }
+                    x if x == (ExtOpCode::MUL as u8) => { let (res, carry) = a.overflowing_mul(val); Some(logic(self, res, carry)) }
+                    x if x == (ExtOpCode::INC as u8) => { Some(add(self, a, A::from_i64(1))) }
+                    x if x == (ExtOpCode::DEC as u8) => { Some(sub(self, a, A::from_i64(1))) }
+                    x if x == (ExtOpCode::SWP as u8) && reg != 0x0f => { self.reg_write(reg as usize, a); Some(val) }
+                    x if x == (ExtOpCode::LOX as u8) || x == (ExtOpCode::STX as u8) || x == (ExtOpCode::LOI as u8) || x == (ExtOpCode::STI as u8) => {
+                        let base = (byte >> 4) as usize;
+                        if base < REGS {
+                            let b = self.reg_read(base);
+                            let (addr, overflow) = if x == (ExtOpCode::LOX as u8) || x == (ExtOpCode::STX as u8) { b.overflowing_add(offset) } else { (b, false) };
+                            if overflow {
+                                self.error = RuntimeError::MemoryOutOfBounds; self.operand = Some(b.to_i64() + offset.to_i64());
+                            } else if let Some(addr) = memchk(self, addr) {
+                                if x == (ExtOpCode::LOI as u8) || x == (ExtOpCode::STI as u8) { self.reg_write(base, b.overflowing_add(A::from_i64(1)).0); }
+                                if x == (ExtOpCode::LOX as u8) || x == (ExtOpCode::LOI as u8) { let a = self.mem_read(addr); self.reg_write(self.processor.act_reg, a); } else { let a = self.reg_read(self.processor.act_reg); self.mem_write(addr, a); }
+                            }
+                        } else { self.error = RuntimeError::RegisterOutOfBounds; self.operand = Some(base as i64); }
+                        None
+                    }
+                    x if x == (ExtOpCode::JCC as u8) => {
+                        match self.processor.condition(byte >> 4) {
+                            Some(true) => { jmpchk(self, offset, false); }
+                            Some(false) => {}
+                            None => { self.error = RuntimeError::IllegalInstruction; }
+                        }
+                        None
+                    }
+                    x if x == (ExtOpCode::DJN as u8) => {
+                        let counter = (byte >> 4) as usize;
+                        if counter < REGS {
+                            let c = self.reg_read(counter).overflowing_sub(A::from_i64(1)).0;
+                            self.reg_write(counter, c);
+                            if c != A::ZERO { jmpchk(self, offset, false); }
+                        } else { self.error = RuntimeError::RegisterOutOfBounds; self.operand = Some(counter as i64); }
+                        None
+                    }
+                    x if x == (ExtOpCode::FAR as u8) => {
+                        match byte >> 4 {
+                            x if x == (OpCode::CAL as u8) => { jmpabs(self, far, true); }
+                            x if x == (OpCode::JMP as u8) => { jmpabs(self, far, false); }
+                            x if x == (OpCode::JPC as u8) => { if self.processor.carry { jmpabs(self, far, false); } }
+                            x if x == (OpCode::JPZ as u8) => { if self.processor.zero { jmpabs(self, far, false); } }
+                            x if x == (OpCode::JPS as u8) => { if self.processor.sign { jmpabs(self, far, false); } }
+                            cond => match self.processor.condition(cond) {
+                                Some(true) => { jmpabs(self, far, false); }
+                                Some(false) => {}
+                                None => { self.error = RuntimeError::IllegalInstruction; }
+                            }
+                        }
+                        None
+                    }
+                    _ => { self.error = RuntimeError::IllegalInstruction; None }
+                };
+                if let Some(res) = res { self.reg_write(self.processor.act_reg, res); }
+            }
             x if x == (OpCode::INT as u8) => {
-                let int_no = reg as usize;                
-                if int_no < interrupts.len() {
-                    interrupts[int_no].call(self);                    
+                let function = self.stack_peek().to_i64();
+                interrupt = Some((reg, function));
+                let mut int_no = self.bindings.get(reg as usize).copied().unwrap_or(UNBOUND) as usize;
+                // the host may pass the interrupts in another order than they were bound in
+                let import = self.program.imports().nth(reg as usize);
+                if let Some(name) = import && interrupts.get(int_no).is_none_or(|int| int.name() != name) {
+                    int_no = interrupts.iter().position(|int| int.name() == name).filter(|i| *i < UNBOUND as usize).unwrap_or(UNBOUND as usize);
+                    if int_no != UNBOUND as usize { self.bindings[reg as usize] = int_no as u8; }
+                }
+                if import.is_some() && int_no == UNBOUND as usize {
+                    self.error = RuntimeError::MissingInterrupt;
+                }else if int_no < interrupts.len() {
+                    let depth = self.stack_depth();
+                    let def = interrupts[int_no].def();
+                    let arity = if def.functions.is_empty() { Some(None) } else { def.function(self.stack_peek()).map(|f| Some((f.arguments, f.returns))) };
+                    match arity {
+                        None => { self.error = RuntimeError::UnimplementedInterruptFunc; }
+                        Some(Some((args, _))) if depth < args + 1 => { self.error = RuntimeError::InterruptArityMismatch; }
+                        _ => {
+                            observe!(self, interrupt_enter(reg, function));
+                            interrupts[int_no].call(self);
+                            observe!(self, interrupt_exit(reg, function));
+                            if let Some(Some((args, rets))) = arity && self.error == RuntimeError::NoError && self.stack_depth() + args + 1 != depth + rets {
+                                self.error = RuntimeError::InterruptArityMismatch;
+                            }
+                        }
+                    }
                 }else{                        
                     self.error = RuntimeError::UnhandledInterrupt;
                 }
@@ -188,10 +468,27 @@ impl <'a> VirtMach <'_> {
             0x0f => {
                 let op = byte;
                 match op {                    
-                    x if x == (OpCode::RET as u8) => { let addr = self.stack_pop(); if addr >= 0 { self.processor.prog_cnt = addr as usize; } else { self.error = RuntimeError::InstructionPointerOutOfBounds }  }                                      
-                    x if x == (OpCode::CLR as u8) => { self.processor.zero = false; self.processor.carry = false; self.processor.carry = false;  }                                      
-                    x if x == (OpCode::INV as u8) => { self.processor.zero = !self.processor.zero; self.processor.carry = !self.processor.carry; self.processor.sign = !self.processor.sign; }                                      
-                    x if x == (OpCode::NEG as u8) => { let res = self.registers[self.processor.act_reg].neg(); self.registers[self.processor.act_reg] = res; self.processor.sign = res < 0; }                                      
+                    x if x == (OpCode::RET as u8) => { let addr = self.stack_pop(); if let Some(addr) = addr.to_usize() { self.processor.prog_cnt = addr; } else { self.error = RuntimeError::InstructionPointerOutOfBounds; self.operand = Some(addr.to_i64()); }  }                                      
+                    x if x == (OpCode::CLR as u8) => { self.processor.zero = false; self.processor.carry = false; self.processor.sign = false; self.processor.overflow = false; }                                      
+                    x if x == (OpCode::INV as u8) => { self.processor.zero = !self.processor.zero; self.processor.carry = !self.processor.carry; self.processor.sign = !self.processor.sign; self.processor.overflow = !self.processor.overflow; }                                      
+                    x if x == (OpCode::EI as u8) => { self.processor.irq_enabled = true; }
+                    x if x == (OpCode::DI as u8) => { self.processor.irq_enabled = false; }
+                    x if x == (OpCode::RTI as u8) => { let addr = self.stack_pop(); let status = self.stack_pop().to_i64() as u8; let reg = (status >> 4) as usize; if reg >= REGS { self.error = RuntimeError::RegisterOutOfBounds; self.operand = Some(reg as i64); } else if let Some(addr) = addr.to_usize() { self.processor.prog_cnt = addr; self.processor.set_flags(status & 0x0f | Processor::IRQ_ENABLED); self.processor.act_reg = reg; } else { self.error = RuntimeError::InstructionPointerOutOfBounds; self.operand = Some(addr.to_i64()); } }
+                    x if x == (OpCode::WFI as u8) => { let ticks = self.reg_read(self.processor.act_reg).to_i64(); self.irq.deadline = if ticks > 0 { Some(self.irq.clock.saturating_add(ticks as u64)) } else { None }; self.state = Runtime::Wfi; }
+                    x if x == (OpCode::IVT as u8) => {
+                        if self.processor.prog_cnt + A::SIZE > instructions.len() { self.error = RuntimeError::ProgramOutOfBounds; self.operand = Some(self.processor.prog_cnt as i64); }
+                        else {
+                            let offset: A = instructions[self.processor.prog_cnt ..].as_ref().get_atom();
+                            self.processor.prog_cnt += A::SIZE;
+                            let line = self.reg_read(self.processor.act_reg);
+                            match (line.to_usize(), usize::try_from(self.processor.prog_cnt as i64 + offset.to_offset())) {
+                                (Some(line), Ok(vector)) if line < IRQ_MAX => { self.history.vector(line, self.irq.vectors[line]); self.irq.vectors[line] = Some(vector); }
+                                (Some(_), Ok(_)) | (None, _) => { self.error = RuntimeError::IrqOutOfBounds; self.operand = Some(line.to_i64()); }
+                                (_, Err(_)) => { self.error = RuntimeError::InstructionPointerOutOfBounds; self.operand = Some(self.processor.prog_cnt as i64 + offset.to_offset()); }
+                            }
+                        }
+                    }
+                    x if x == (OpCode::NEG as u8) => { let res = self.reg_read(self.processor.act_reg).wrapping_neg(); self.reg_write(self.processor.act_reg, res); self.processor.sign = res.sign_bit(); }                                      
                     x if x == (OpCode::BRK as u8) => { if self.halt_on_break == true { self.state = Runtime::Hlt; } }
                     x if x == (OpCode::HLT as u8) => { self.state = Runtime::Hlt; }
                     x if x == (OpCode::END as u8) => { self.state = Runtime::Stp; }
@@ -204,30 +501,78 @@ impl <'a> VirtMach <'_> {
         if self.error != RuntimeError::NoError {
             self.state = Runtime::Err;
             self.processor.prog_cnt = inst_pos;
+            self.fault = Some(Fault { error: self.error.clone(), pc: inst_pos, opcode: Some(byte), operand: self.operand.take(), interrupt });
+            observe!(self, fault(self.fault.as_ref().unwrap()));
         }
 
         self.cycle_cnt += 1;
+        self.deliver_irq();
+
+        // a hit by an instruction that halted, ended or faulted is dropped, its stop reason wins
+        let hit = self.debug.hit.take();
+        if let Some(id) = hit && self.state == Runtime::Run {
+            self.state = Runtime::Hlt;
+            return StopReason::Watchpoint(id, inst_pos);
+        }
+
+        if byte == OpCode::BRK as u8 && self.state == Runtime::Hlt {
+            return StopReason::Breakpoint(inst_pos);
+        }
+        return self.stop_reason();
+    }
+
+    /// Sets the observer called on execution events, see `Observer`.
+    #[cfg(feature = "observer")]
+    pub fn set_observer(&mut self, observer: Option<&'a mut dyn Observer<A>>) {
+        self.observer = observer;
+    }
+
+    /// The stop reason corresponding to the current state.
+    pub fn stop_reason(&self) -> StopReason {
+        return match self.state {
+            Runtime::Run => StopReason::BudgetExhausted,
+            Runtime::Hlt => StopReason::Halted,
+            Runtime::Stp | Runtime::Ini => StopReason::Ended,
+            Runtime::Err => StopReason::Fault(self.error.clone(), self.processor.prog_cnt),
+            Runtime::Wfi => StopReason::WaitingForInterrupt
+        };
+    }
+
+    /// Makes `brk` halt the VM and `run` return `StopReason::Breakpoint`, otherwise it is ignored.
+    pub fn set_halt_on_break(&mut self, halt: bool) {
+        self.halt_on_break = halt;
     }
 
     pub fn reset(&mut self) {
-        self.processor = Processor::default();   
+        self.processor = Processor::new(self.memory.len());   
+        self.processor.prog_cnt = self.program.entry();
         self.state = Runtime::Hlt;
         self.error = RuntimeError::NoError;        
+        self.fault = None;
+        self.debug.resume = false;
+        self.debug.hit = None;
+        self.history.clear();
+        self.irq = Irq { clock: self.irq.clock, ..Irq::new() };
         self.cycle_cnt = 0;
-        self.memory.fill(0);
+        self.memory.fill(A::ZERO);
     }
 
-    pub fn run (&mut self, max_ops: usize, interrupts: &mut [& mut dyn interrupts::SoftInterrupt]) {
+    /// Runs up to `max_ops` instructions, or until the VM stops if 0. The program's imports
+    /// are bound against `interrupts` first, so their order does not matter.
+    pub fn run (&mut self, max_ops: usize, interrupts: &mut [& mut dyn interrupts::SoftInterrupt<A, REGS>]) -> StopReason {
         let mut op_cnt = 0;
 
-        if self.state == Runtime::Hlt {
+        self.wake_on_irq();
+        if self.state == Runtime::Hlt && self.bind(interrupts).is_ok() {
             self.state = Runtime::Run;
         }
 
+        let mut reason = self.stop_reason();
         while (max_ops == 0 || op_cnt < max_ops) && self.state == Runtime::Run {
-            self.step(interrupts);
+            reason = self.step(interrupts);
             op_cnt += 1;            
         }    
+        return reason;
     }
 
     pub fn pause(&mut self) {
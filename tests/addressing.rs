@@ -0,0 +1,56 @@
+//! Indexed and post-increment addressing have to reach the right cells and stay bounds checked.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, RuntimeError};
+
+const LISTING: &str = "
+    reg r1
+    set #0
+    reg r2
+    set #4
+loop:
+    reg r3
+    loa [r1]+
+    reg r0
+    add r3
+    reg r2
+    sub #1
+    jpz done
+    jmp loop
+done:
+    reg r0
+    sto [r1+#2]
+    reg r4
+    loa [r1]
+    end
+";
+
+#[test]
+fn indexed_and_post_increment_addressing() {
+    let program = VirtMach::<i16>::compile("addressing", LISTING, &[]).unwrap();
+    let mut memory = [0i16;16];
+    {
+        let mut vm = VirtMach::<i16>::new(&mut memory);
+        vm.load_program(program.as_program(), &[]).unwrap();
+        vm.memory[..5].copy_from_slice(&[3, 5, 7, 11, 13]);
+        assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+        assert_eq!(vm.registers[..5], [26, 4, 0, 11, 13]);
+    }
+    assert_eq!(memory[6], 26);
+
+    let mut line = String::new();
+    VirtMach::<i16>::decompile(&program.as_program(), 9, &mut line);
+    assert_eq!(line, "loa [r1]+");
+}
+
+#[test]
+fn indexed_addressing_is_bounds_checked() {
+    let program = VirtMach::<i16>::compile("addressing", "reg r1\nset #10\nloa [r1+#6]\nend", &[]).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Fault(RuntimeError::MemoryOutOfBounds, 4));
+
+    assert!(VirtMach::<i16>::compile("addressing", "add [r1]+", &[]).is_err());
+}
@@ -0,0 +1,58 @@
+//! `load_program` has to reject binaries that do not fit the VM or were damaged.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, Program, RuntimeError, StopReason, VMAtom};
+use virtmach::binary::{Header, HEADER_SIZE, FORMAT_VERSION};
+
+const LISTING: &str = "
+    reg r5
+    set #7
+    end
+";
+
+#[test]
+fn register_count_is_recorded() {
+    let program = VirtMach::<VMAtom, 8>::compile("regs", LISTING, &[]).unwrap();
+    assert_eq!(Header::parse(&program.data).unwrap().regs, 8);
+
+    let mut memory = [0;8];
+    let mut vm = VirtMach::<VMAtom, 10>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[5], 7);
+
+    let mut memory = [0;8];
+    let mut vm = VirtMach::<VMAtom, 6>::new(&mut memory);
+    assert_eq!(vm.load_program(program.as_program(), &[]), Err(RuntimeError::RegisterOutOfBounds));
+}
+
+fn load(data: &[u8]) -> Result<(), RuntimeError> {
+    let mut memory = [0;8];
+    let mut vm = <VirtMach>::new(&mut memory);
+    vm.load_program(Program::new(0, "damaged", data), &[])
+}
+
+#[test]
+fn damaged_binaries_are_rejected() {
+    let program = <VirtMach>::compile("damaged", LISTING, &[]).unwrap();
+    assert_eq!(load(&program.data), Ok(()));
+
+    let truncated = &program.data[..program.data.len() - 3];
+    assert_eq!(load(truncated), Err(RuntimeError::TruncatedProgram));
+    assert_eq!(load(&program.data[..HEADER_SIZE - 1]), Err(RuntimeError::MalformedHeader));
+
+    let mut flipped = program.data.clone();
+    flipped[HEADER_SIZE + 1] ^= 0x10;
+    assert_eq!(load(&flipped), Err(RuntimeError::ChecksumMismatch));
+
+    let mut version = program.data.clone();
+    version[4] = FORMAT_VERSION + 1;
+    assert_eq!(load(&version), Err(RuntimeError::UnsupportedVersion));
+
+    // a code length reaching past the end of the address space
+    let mut length = program.data.clone();
+    length[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(load(&length), Err(RuntimeError::TruncatedProgram));
+    assert!(Program::<VMAtom>::new(0, "damaged", &length).code().is_empty());
+}
@@ -0,0 +1,102 @@
+//! Bytecode has to decode the same on every host and with or without `std`.
+//!
+//! Run with `--no-default-features` as well as with `--features compile` to cover both
+//! the `bytes` and the plain slice readers and writers.
+
+use virtmach::{Atom, VAtom, VAtomMut, VirtMach, Program, Runtime};
+use virtmach::binary::LITTLE_ENDIAN;
+
+#[cfg(feature = "compile")]
+const LISTING: &str = "
+#ent start
+loop:
+    reg r1
+    add #1000
+    reg r0
+    sub #1
+    jpz done
+    jmp loop
+start:
+    reg r1
+    set #-300
+    reg r0
+    set #3
+    jmp loop
+done:
+    reg r1
+    sto #0
+    end
+";
+
+/// `LISTING` compiled for `i16`.
+const BINARY: [u8;51] = [
+    0x56, 0x4d, 0x42, 0x43, 0x03, 0x00, 0x02, 0x0f, 0x1e, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x00,
+    0x1d, 0xc2, 0x5e, 0xed,
+    0x10, 0xf6, 0xe8, 0x03, 0x00, 0xf7, 0x01, 0x00, 0xfb, 0x0e, 0x00, 0xfa, 0xf2, 0xff,
+    0x10, 0xf1, 0xd4, 0xfe, 0x00, 0xf1, 0x03, 0x00, 0xfa, 0xe7, 0xff,
+    0x10, 0xf3, 0x00, 0x00, 0xff,
+    0x00
+];
+
+fn put<A: Atom>(a: A) -> [u8;8] {
+    let mut buf = [0u8;8];
+    (&mut buf[..]).put_atom(a);
+    buf
+}
+
+fn get<A: Atom>(bytes: &[u8]) -> A {
+    let mut bytes = bytes;
+    bytes.get_atom()
+}
+
+#[test]
+fn atoms_are_little_endian() {
+    assert_eq!(put(-300i16)[..2], [0xd4, 0xfe]);
+    assert_eq!(put(0x1234u16)[..2], [0x34, 0x12]);
+    assert_eq!(put(-2i32)[..4], [0xfe, 0xff, 0xff, 0xff]);
+    assert_eq!(put(0x0102_0304_0506_0708i64), [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+    assert_eq!(put(-1i8)[..1], [0xff]);
+
+    assert_eq!(get::<i16>(&[0xd4, 0xfe]), -300);
+    assert_eq!(get::<u16>(&[0x34, 0x12]), 0x1234);
+    assert_eq!(get::<i32>(&[0xfe, 0xff, 0xff, 0xff]), -2);
+    assert_eq!(get::<i64>(&[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]), 0x0102_0304_0506_0708);
+}
+
+#[test]
+fn atoms_round_trip() {
+    for v in [i32::MIN, -65536, -1, 0, 1, 258, i32::MAX] {
+        assert_eq!(get::<i32>(&put(v)), v);
+    }
+    for v in [u32::MIN, 1, 0x8000_0000, u32::MAX] {
+        assert_eq!(get::<u32>(&put(v)), v);
+    }
+}
+
+#[test]
+fn header_declares_little_endian() {
+    assert_eq!(BINARY[5], LITTLE_ENDIAN);
+}
+
+#[test]
+fn binary_runs() {
+    let mut memory = [0i16;4];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(Program::new(0, "byte_order", &BINARY), &[]).unwrap();
+    vm.run(0, &mut []);
+
+    assert!(matches!(vm.state, Runtime::Stp));
+    assert_eq!(vm.registers[..2], [0, 2700]);
+    assert_eq!(vm.cycle_cnt, 25);
+    assert_eq!(memory[0], 2700);
+}
+
+#[cfg(feature = "compile")]
+#[test]
+fn compiler_emits_little_endian() {
+    use virtmach::binary::HEADER_SIZE;
+
+    let program = VirtMach::<i16>::compile("byte_order", LISTING, &[]).unwrap();
+    assert_eq!(program.data, BINARY);
+    assert_eq!(program.as_program().code(), &BINARY[HEADER_SIZE..BINARY.len() - 1]);
+}
@@ -0,0 +1,47 @@
+//! Signed relations have to follow sign and overflow, unsigned ones carry, after a `cmp`.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, Atom, StopReason};
+
+const RELATIONS: [&str;8] = ["jlt", "jle", "jgt", "jge", "jlo", "jls", "jhi", "jhs"];
+
+/// Runs `cmp a, b` followed by each relation jump, returning which ones were taken.
+fn taken<A: Atom>(a: &str, b: &str) -> [bool;8] {
+    let mut taken = [false;8];
+    for (i, relation) in RELATIONS.iter().enumerate() {
+        let listing = format!("reg r1\nset #{a}\ncmp #{b}\n{relation} yes\nend\nyes:\nreg r0\nset #1\nend\n");
+        let program = VirtMach::<A>::compile("conditions", &listing, &[]).unwrap();
+        let mut memory = [A::ZERO;4];
+        let mut vm = VirtMach::<A>::new(&mut memory);
+        vm.load_program(program.as_program(), &[]).unwrap();
+        assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+        taken[i] = vm.registers[0] != A::ZERO;
+    }
+    taken
+}
+
+#[test]
+fn signed_relations() {
+    // -1 < 1 signed, but 0xffff > 1 unsigned
+    assert_eq!(taken::<i16>("-1", "1"), [true, true, false, false, false, false, true, true]);
+    // overflows: 32767 - -1
+    assert_eq!(taken::<i16>("32767", "-1"), [false, false, true, true, true, true, false, false]);
+    assert_eq!(taken::<i16>("5", "5"), [false, true, false, true, false, true, false, true]);
+}
+
+#[test]
+fn unsigned_relations() {
+    // 200 > 100 unsigned, but -56 < 100 signed
+    assert_eq!(taken::<u8>("200", "100"), [true, true, false, false, false, false, true, true]);
+    assert_eq!(taken::<u8>("3", "5"), [true, true, false, false, true, true, false, false]);
+}
+
+#[test]
+fn clr_clears_all_flags() {
+    let program = VirtMach::<i16>::compile("conditions", "reg r0\nset #-5\nsub #1\nclr\njps bad\njpc bad\njpz bad\njlt bad\nend\nbad:\nhlt", &[]).unwrap();
+    let mut memory = [0i16;4];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+}
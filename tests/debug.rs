@@ -0,0 +1,87 @@
+//! Breakpoints stop before and watchpoints after the instruction concerned.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, RuntimeError, Watchpoint, WatchTarget, WatchCondition};
+use virtmach::interrupts::{SoftInterrupt, Proc, BASE_INTERRUPT_DEFS};
+
+const LISTING: &str = "
+    reg r0
+    set #3
+    sto #2
+    reg r1
+    loa #2
+    end
+";
+
+#[test]
+fn breakpoint_stops_before_instruction() {
+    let program = VirtMach::<i16>::compile("debug", LISTING, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    assert!(vm.add_breakpoint(4));
+    assert_eq!(vm.run(0, &mut []), StopReason::Breakpoint(4));
+    assert_eq!(vm.cycle_cnt, 2);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[1], 3);
+}
+
+#[test]
+fn watchpoints_stop_after_access() {
+    let program = VirtMach::<i16>::compile("debug", LISTING, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    let write = vm.add_watchpoint(Watchpoint { target: WatchTarget::Memory(2), condition: WatchCondition::Value(3) }).unwrap();
+    let read = vm.add_watchpoint(Watchpoint { target: WatchTarget::Memory(2), condition: WatchCondition::Read }).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Watchpoint(write, 4));
+    assert_eq!(vm.run(0, &mut []), StopReason::Watchpoint(read, 8));
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+}
+
+#[test]
+fn int_slot_is_not_a_register_read() {
+    let program = VirtMach::<i16>::compile("debug", "r1 = proc.atom_size()\nend", BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut Proc {}];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+
+    vm.add_watchpoint(Watchpoint { target: WatchTarget::Register(0), condition: WatchCondition::Read }).unwrap();
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    assert_eq!(vm.registers[1], 2);
+}
+
+#[test]
+fn watchpoint_hit_by_faulting_instruction_is_dropped() {
+    let program = VirtMach::<i16>::compile("debug", "reg r0\nset #4\nsto #7\nend", &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    let id = vm.add_watchpoint(Watchpoint { target: WatchTarget::Memory(7), condition: WatchCondition::Write }).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Fault(RuntimeError::HeapCrash, 4));
+
+    vm.remove_watchpoint(id);
+    let id = vm.add_watchpoint(Watchpoint { target: WatchTarget::Register(0), condition: WatchCondition::Write }).unwrap();
+    vm.reset();
+    assert_eq!(vm.run(0, &mut []), StopReason::Watchpoint(id, 1));
+}
+
+#[test]
+fn extended_ops_read_active_register_only_when_used() {
+    let program = VirtMach::<i16>::compile("debug", "reg r1\nset #3\nreg r0\nloop:\ndjn r1 loop\njlt next\nnext:\nloa [r1+#2]\nsto [r1+#3]\nend", &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    let read = vm.add_watchpoint(Watchpoint { target: WatchTarget::Register(0), condition: WatchCondition::Read }).unwrap();
+    let StopReason::Watchpoint(id, pc) = vm.run(0, &mut []) else { panic!("expected the store to read r0"); };
+    assert_eq!(id, read);
+    let mut line = String::new();
+    VirtMach::<i16>::decompile(&program.as_program(), pc, &mut line);
+    assert!(line.starts_with("sto"));
+}
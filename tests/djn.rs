@@ -0,0 +1,33 @@
+//! `djn` has to count its register down and loop until it reaches zero.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason};
+
+const LISTING: &str = "
+    reg r1
+    set #5
+    reg r0
+loop:
+    add #3
+    djn r1 loop
+    end
+";
+
+#[test]
+fn djn_loops_until_zero() {
+    let program = VirtMach::<i16>::compile("djn", LISTING, &[]).unwrap();
+    let mut memory = [0i16;4];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    vm.run(4, &mut []);
+    let mut dashboard = String::new();
+    vm.write_dashboard(&mut dashboard, 0, 2);
+    assert!(dashboard.contains(">   8:djn r1 #-7"));
+
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[..2], [15, 0]);
+    assert_eq!(vm.cycle_cnt, 3 + 5 * 2 + 1);
+    assert!(VirtMach::<i16>::compile("djn", "loop:\n    djn #1 loop", &[]).is_err());
+}
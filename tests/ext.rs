@@ -0,0 +1,53 @@
+//! The extended page has to compile, execute and decompile like the base instructions.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason};
+
+const LISTING: &str = "
+    reg r1
+    set #12
+    reg r0
+    set #10
+    and #6
+    or #1
+    xor r1
+    shl #2
+    mul #3
+    inc
+    swap r1
+    shr #1
+    dec
+    cmp #5
+    jpz equal
+    end
+equal:
+    reg r2
+    set #1
+    end
+";
+
+#[test]
+fn ext_page_executes() {
+    let program = VirtMach::<i16>::compile("ext", LISTING, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+
+    // (((10 & 6) | 1) ^ 12) << 2 = 60, * 3 + 1 = 181 swapped into r1, 12 >> 1 - 1 = 5
+    assert_eq!(vm.registers[..3], [5, 181, 1]);
+}
+
+#[test]
+fn ext_page_decompiles() {
+    let program = VirtMach::<i16>::compile("ext", LISTING, &[]).unwrap();
+    let mut lines = vec![];
+    let mut position = 0;
+    while position < program.as_program().code().len() {
+        let mut line = String::new();
+        position = VirtMach::<i16>::decompile(&program.as_program(), position, &mut line);
+        lines.push(line.trim_end().to_string());
+    }
+    assert_eq!(lines[4..14], ["and #6", "or  #1", "xor r1", "shl #2", "mul #3", "inc", "swap r1", "shr #1", "dec", "cmp #5"]);
+}
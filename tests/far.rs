@@ -0,0 +1,42 @@
+//! Branches beyond the atom's offset range have to be relaxed into far jumps, or rejected.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, ListingError};
+
+fn listing(filler: usize) -> String {
+    let mut listing = String::from("    cal sub\n    jmp skip\n");
+    listing.push_str(&"    add #1\n".repeat(filler));
+    listing.push_str("skip:\n    reg r1\n    set #1\n    end\nsub:\n    reg r2\n    set #2\n    ret\n");
+    listing
+}
+
+#[test]
+fn far_jumps_are_relaxed() {
+    let listing = listing(11000);
+    let program = VirtMach::<i16>::compile("far", &listing, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[..3], [0, 1, 2]);
+    assert_eq!(vm.cycle_cnt, 8);
+
+    let mut line = String::new();
+    VirtMach::<i16>::decompile(&program.as_program(), 0, &mut line);
+    assert!(line.starts_with("cal @"));
+}
+
+#[test]
+fn jumps_beyond_far_range_are_rejected() {
+    let listing = listing(25000);
+    assert!(matches!(VirtMach::<i16>::compile("far", &listing, &[]), Err(ListingError::LabelOutOfRange(1, "sub"))));
+}
+
+#[test]
+fn calls_beyond_atom_range_are_rejected() {
+    // the return position of the call is 33005, past i16::MAX
+    let mut listing = "    add #1\n".repeat(11000);
+    listing.push_str("    cal sub\n    end\nsub:\n    ret\n");
+    assert!(matches!(VirtMach::<i16>::compile("far", &listing, &[]), Err(ListingError::LabelOutOfRange(11001, "sub"))));
+}
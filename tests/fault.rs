@@ -0,0 +1,50 @@
+//! A fault has to record where and on what the VM failed, until the next reset.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, RuntimeError, Fault};
+use virtmach::interrupts::{SoftInterrupt, Math, BASE_INTERRUPT_DEFS};
+
+#[test]
+fn memory_fault_names_address() {
+    let program = VirtMach::<i16>::compile("fault", "reg r0\nloa #100\nend", &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    let StopReason::Fault(error, pc) = vm.run(0, &mut []) else { panic!("expected a fault"); };
+    let fault = vm.fault.clone().unwrap();
+    assert_eq!((fault.error.clone(), fault.pc), (error, pc));
+    assert_eq!(fault.pc, 1);
+    assert_eq!(fault.opcode, Some(program.as_program().code()[1]));
+    assert_eq!(fault.operand, Some(100));
+    assert_eq!(fault.interrupt, None);
+    assert_eq!(fault.to_string(), format!("{:?} at 0001, opcode {:02x}, operand 100", fault.error, fault.opcode.unwrap()));
+
+    vm.reset();
+    assert_eq!(vm.fault, None);
+}
+
+#[test]
+fn interrupt_fault_names_slot_and_function() {
+    let program = VirtMach::<i16>::compile("fault", "reg r0\nr0 = math.div(#1, #0)\nend", BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut math = Math {};
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut math];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+
+    assert!(matches!(vm.run(0, &mut interrupts), StopReason::Fault(RuntimeError::InterruptError, _)));
+    let fault = vm.fault.clone().unwrap();
+    assert_eq!(fault.interrupt, Some((0, 7)));
+    assert!(fault.to_string().ends_with(", int 0 function 7"));
+    assert!(!fault.to_string().contains('\n'));
+}
+
+#[test]
+fn display_is_one_line() {
+    let fault = Fault { error: RuntimeError::HeapUnderflow, pc: 0x1a, opcode: Some(0x3c), operand: Some(-1), interrupt: Some((2, 5)) };
+    assert_eq!(fault.to_string(), "HeapUnderflow at 001a, opcode 3c, operand -1, int 2 function 5");
+    let fault = Fault { error: RuntimeError::ChecksumMismatch, pc: 0, opcode: None, operand: None, interrupt: None };
+    assert_eq!(fault.to_string(), "ChecksumMismatch at 0000");
+}
@@ -0,0 +1,71 @@
+//! Stepping back has to restore exactly the state before each undone instruction.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, HistoryEntry, StopReason};
+
+const LISTING: &str = "
+    reg r1
+    set #1
+loop:
+    reg r0
+    add #1
+    psh r0
+    cal double
+    pop r2
+    sto #1
+    jmp loop
+double:
+    reg r1
+    add r1
+    ret
+";
+
+fn state(vm: &VirtMach<i16>) -> (Vec<i16>, Vec<i16>, usize, usize) {
+    (vm.registers.to_vec(), vm.memory.to_vec(), vm.cycle_cnt, vm.stack_depth())
+}
+
+#[test]
+fn step_back_restores_previous_states() {
+    let program = VirtMach::<i16>::compile("history", LISTING, &[]).unwrap();
+    let mut history = [HistoryEntry::EMPTY;24];
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    vm.set_history(&mut history);
+
+    let mut states = vec![];
+    for _ in 0 .. 20 {
+        states.push(state(&vm));
+        vm.run(1, &mut []);
+    }
+
+    let depth = vm.history_depth();
+    assert!(depth > 0 && depth < 20);
+    for expected in states.iter().rev().take(depth) {
+        assert!(vm.step_back());
+        assert_eq!(&state(&vm), expected);
+    }
+    assert!(!vm.step_back());
+    assert_eq!(vm.run(1, &mut []), StopReason::BudgetExhausted);
+}
+
+#[test]
+fn run_back_to_stops_at_position() {
+    let program = VirtMach::<i16>::compile("history", LISTING, &[]).unwrap();
+    let mut history = [HistoryEntry::EMPTY;256];
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    vm.set_history(&mut history);
+
+    vm.run(17, &mut []);
+
+    // `add #1`, first reached after `reg r1`, `set #1`, `reg r0` and then every 10 instructions
+    assert!(vm.run_back_to(5));
+    assert_eq!(vm.cycle_cnt, 13);
+    assert_eq!(vm.registers[..3], [1, 2, 1]);
+    assert!(vm.run_back_to(5));
+    assert_eq!(vm.cycle_cnt, 3);
+    assert!(!vm.run_back_to(5));
+}
@@ -0,0 +1,48 @@
+//! Interrupt calls have to reach the interrupt named by the import, in whatever order the
+//! host passes the interrupts.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, RuntimeError};
+use virtmach::interrupts::{Proc, Math, BASE_INTERRUPT_DEFS};
+
+const LISTING: &str = "
+    #req proc
+    #req math
+    r0 = proc.atom_size()
+    r1 = math.mul(#3, #4)
+    end
+";
+
+#[test]
+fn reordered_interrupts_are_rebound() {
+    let program = VirtMach::<i16>::compile("imports", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let (mut proc, mut math) = (Proc {}, Math {});
+    vm.load_program(program.as_program(), &[&mut proc, &mut math]).unwrap();
+
+    assert_eq!(vm.run(3, &mut [&mut proc, &mut math]), StopReason::BudgetExhausted);
+    assert_eq!(vm.run(0, &mut [&mut math, &mut proc]), StopReason::Ended);
+    assert_eq!(vm.registers[..2], [2, 12]);
+}
+
+#[test]
+fn interrupt_missing_after_start_faults() {
+    let program = VirtMach::<i16>::compile("imports", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let (mut proc, mut math) = (Proc {}, Math {});
+    vm.load_program(program.as_program(), &[&mut proc, &mut math]).unwrap();
+
+    assert_eq!(vm.run(3, &mut [&mut proc, &mut math]), StopReason::BudgetExhausted);
+    assert!(matches!(vm.run(0, &mut [&mut proc]), StopReason::Fault(RuntimeError::MissingInterrupt, _)));
+}
+
+#[test]
+fn missing_interrupt_fails_load() {
+    let program = VirtMach::<i16>::compile("imports", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    assert_eq!(vm.load_program(program.as_program(), &[&mut Proc {}]), Err(RuntimeError::MissingInterrupt));
+}
@@ -0,0 +1,72 @@
+//! `#[interrupt]` has to generate a definition and dispatch matching the impl block.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, VMAtom, StopReason, RuntimeError, interrupt};
+use virtmach::interrupts::SoftInterrupt;
+
+struct Counter {
+    count: VMAtom
+}
+
+#[interrupt]
+impl Counter {
+    /// step
+    fn add(&mut self, step: VMAtom) -> VMAtom {
+        self.count += step;
+        self.count
+    }
+
+    /// count and its double
+    fn both(&self) -> (VMAtom, VMAtom) {
+        (self.count, self.count * 2)
+    }
+
+    /// a / b
+    #[no(8)]
+    fn div(&self, a: VMAtom, b: VMAtom) -> Result<VMAtom, RuntimeError> {
+        if b == 0 { Err(RuntimeError::InterruptError) } else { Ok(a / b) }
+    }
+
+    /// cells below the stack
+    fn heap(&self, vm: &mut VirtMach) -> VMAtom {
+        vm.stack_depth() as VMAtom
+    }
+}
+
+#[test]
+fn generated_definition() {
+    let def = Counter::DEF;
+    assert_eq!(def.name, "counter");
+    let functions: Vec<_> = def.functions.iter().map(|f| (f.no, f.name, f.arguments, f.returns)).collect();
+    assert_eq!(functions, [(0, "add", 1, 1), (1, "both", 0, 2), (8, "div", 2, 1), (9, "heap", 0, 1)]);
+    assert_eq!(def.functions[2].help, "a / b");
+}
+
+fn run(listing: &str, counter: &mut Counter) -> (StopReason, [VMAtom;4]) {
+    let program = <VirtMach>::compile("macro", listing, &[Counter::DEF]).unwrap();
+    let mut memory = [0;16];
+    let mut vm = <VirtMach>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt; 1] = [counter];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    let reason = vm.run(0, &mut interrupts);
+    (reason, vm.registers[..4].try_into().unwrap())
+}
+
+#[test]
+fn generated_functions_run_in_a_vm() {
+    let mut counter = Counter { count: 0 };
+    let (reason, registers) = run("
+        _ = counter.add(#5)
+        r0 = counter.add(#2)
+        r1, r2 = counter.both()
+        r3 = counter.div(#21, r0)
+        end
+    ", &mut counter);
+    assert_eq!(reason, StopReason::Ended);
+    assert_eq!(registers, [7, 7, 14, 3]);
+    assert_eq!(counter.count, 7);
+
+    let (reason, _) = run("r0 = counter.div(#1, #0)\nend", &mut counter);
+    assert!(matches!(reason, StopReason::Fault(RuntimeError::InterruptError, _)));
+}
@@ -0,0 +1,122 @@
+//! Host-raised IRQs have to enter the registered handler between steps and `rti` has to
+//! return to the interrupted code.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason};
+
+const LISTING: &str = "
+#ent start
+on_tick:
+    reg r1
+    add #1
+    rti
+start:
+    reg r0
+    set #0
+    ivt on_tick
+    ei
+loop:
+    reg r2
+    add #1
+    jmp loop
+";
+
+#[test]
+fn raised_irq_runs_handler() {
+    let program = VirtMach::<i16>::compile("irq", LISTING, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    assert!(!vm.raise_irq(0));
+    assert_eq!(vm.run(10, &mut []), StopReason::BudgetExhausted);
+    assert!(vm.raise_irq(0));
+    assert!(!vm.raise_irq(1));
+    assert!(vm.irq_pending(0));
+
+    vm.run(1, &mut []);
+    assert!(!vm.irq_pending(0));
+    assert_eq!(vm.stack_depth(), 2);
+    vm.run(3, &mut []);
+    assert_eq!(vm.registers[1], 1);
+    assert_eq!(vm.stack_depth(), 0);
+
+    vm.raise_irq(0);
+    vm.run(10, &mut []);
+    assert_eq!(vm.registers[1], 2);
+}
+
+#[test]
+fn disabled_irq_stays_pending() {
+    let program = VirtMach::<i16>::compile("irq", &LISTING.replace("    ei\n", "    di\n"), &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    vm.run(10, &mut []);
+    vm.raise_irq(0);
+    vm.run(10, &mut []);
+    assert!(vm.irq_pending(0));
+    assert_eq!(vm.registers[1], 0);
+}
+
+const WAITING: &str = "
+#ent start
+on_tick:
+    reg r1
+    add #1
+    rti
+start:
+    reg r0
+    set #0
+    ivt on_tick
+    ei
+loop:
+    reg r3
+    wfi
+    reg r2
+    add #1
+    jmp loop
+";
+
+#[test]
+fn wfi_waits_for_irq() {
+    let program = VirtMach::<i16>::compile("wfi", WAITING, &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    let cycles = vm.cycle_cnt;
+    assert_eq!(vm.run(100, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.cycle_cnt, cycles);
+    assert_eq!(vm.wfi_deadline(), None);
+
+    vm.raise_irq(0);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.registers[1..3], [1, 1]);
+
+    vm.wake();
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.registers[1..3], [1, 2]);
+}
+
+#[test]
+fn wfi_times_out() {
+    let program = VirtMach::<i16>::compile("wfi", &WAITING.replace("    reg r3\n", "    reg r3\n    set #50\n"), &[]).unwrap();
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+
+    vm.set_clock(1000);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.wfi_deadline(), Some(1050));
+    vm.set_clock(1049);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.registers[2], 0);
+    vm.set_clock(1050);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.registers[2], 1);
+    assert_eq!(vm.wfi_deadline(), Some(1100));
+}
@@ -0,0 +1,120 @@
+//! Replaying a journal has to reproduce a recorded session without the real interrupts.
+
+#![cfg(feature = "compile")]
+
+use core::cell::RefCell;
+
+use virtmach::{VirtMach, RuntimeError, StopReason};
+use virtmach::interrupts::{SoftInterrupt, Journal, Recorder, Replayer, Random, BASE_INTERRUPT_DEFS};
+
+const LISTING: &str = "
+    #req random
+    reg r2
+    set #8
+loop:
+    r0 = random.range(#0, #1000)
+    reg r1
+    add r0
+    reg r2
+    sub #1
+    jpz done
+    jmp loop
+done:
+    reg r1
+    sto #0
+    end
+";
+
+#[test]
+fn replay_reproduces_recording() {
+    let program = VirtMach::<i16>::compile("journal", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+
+    let mut buf = [0u8;256];
+    let journal = RefCell::new(Journal::new(&mut buf));
+    let mut random = Random::new(7);
+    let mut recorder = Recorder::new(&mut random, &journal);
+    let mut memory = [0i16;16];
+    let (registers, cycles) = {
+        let mut vm = VirtMach::<i16>::new(&mut memory);
+        let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut recorder];
+        vm.load_program(program.as_program(), &interrupts).unwrap();
+        assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+        (vm.registers, vm.cycle_cnt)
+    };
+
+    let mut recording = journal.into_inner().as_bytes().to_vec();
+    let journal = RefCell::new(Journal::load(&mut recording));
+    let random = Random::new(0);
+    let mut replayer = Replayer::new(<Random as SoftInterrupt<i16>>::def(&random), &journal);
+    let mut replayed_memory = [0i16;16];
+    {
+        let mut vm = VirtMach::<i16>::new(&mut replayed_memory);
+        let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut replayer];
+        vm.load_program(program.as_program(), &interrupts).unwrap();
+        assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+
+        assert_eq!(vm.registers, registers);
+        assert_eq!(vm.cycle_cnt, cycles);
+    }
+    assert_eq!(replayed_memory, memory);
+}
+
+#[test]
+fn replay_detects_divergence() {
+    let program = VirtMach::<i16>::compile("journal", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+
+    let journal = RefCell::new(Journal::load(&mut []));
+    let random = Random::new(0);
+    let mut replayer = Replayer::new(<Random as SoftInterrupt<i16>>::def(&random), &journal);
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut replayer];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    assert!(matches!(vm.run(0, &mut interrupts), StopReason::Fault(RuntimeError::ReplayDiverged, _)));
+}
+
+#[test]
+fn replay_reproduces_memory_writes() {
+    let program = VirtMach::<i16>::compile("journal", "_ = random.shuffle(#0, #8)\nend", BASE_INTERRUPT_DEFS).unwrap();
+
+    let mut buf = [0u8;256];
+    let journal = RefCell::new(Journal::new(&mut buf));
+    let mut random = Random::new(7);
+    let mut recorder = Recorder::new(&mut random, &journal);
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut recorder];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    vm.memory[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    let shuffled: [i16;8] = vm.memory[..8].try_into().unwrap();
+    assert_ne!(shuffled, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+    let mut recording = journal.into_inner().as_bytes().to_vec();
+    let journal = RefCell::new(Journal::load(&mut recording));
+    let random = Random::new(0);
+    let mut replayer = Replayer::new(<Random as SoftInterrupt<i16>>::def(&random), &journal);
+    let mut replayed_memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut replayed_memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut replayer];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    vm.memory[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    assert_eq!(vm.memory[..8], shuffled);
+}
+
+#[test]
+fn recording_needs_room_for_the_heap() {
+    let program = VirtMach::<i16>::compile("journal", "_ = random.shuffle(#0, #8)\nend", BASE_INTERRUPT_DEFS).unwrap();
+
+    // too small to hold a copy of the cells below the stack
+    let mut buf = [0u8;8];
+    let journal = RefCell::new(Journal::new(&mut buf));
+    let mut random = Random::new(7);
+    let mut recorder = Recorder::new(&mut random, &journal);
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut recorder];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    assert!(matches!(vm.run(0, &mut interrupts), StopReason::Fault(RuntimeError::BufferTooSmall, _)));
+}
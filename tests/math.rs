@@ -0,0 +1,37 @@
+//! Every function `math` advertises has to be implemented.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, StopReason, RuntimeError};
+use virtmach::interrupts::{SoftInterrupt, Math, BASE_INTERRUPT_DEFS};
+
+fn run(listing: &str) -> (StopReason, [i16;3]) {
+    let program = VirtMach::<i16>::compile("math", listing, BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut math = Math {};
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut math];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    let reason = vm.run(0, &mut interrupts);
+    (reason, [vm.registers[0], vm.registers[1], vm.registers[2]])
+}
+
+#[test]
+fn pow_and_sqr() {
+    let (reason, registers) = run("r0 = math.pow(#3, #4)\nr1 = math.sqr(#80)\nr2 = math.sqr(#81)\nend");
+    assert_eq!(reason, StopReason::Ended);
+    assert_eq!(registers, [81, 8, 9]);
+}
+
+#[test]
+fn pow_overflow_sets_carry() {
+    let (reason, registers) = run("r0 = math.pow(#2, #16)\njpc carry\nend\ncarry:\nreg r1\nset #1\nend");
+    assert_eq!(reason, StopReason::Ended);
+    assert_eq!(registers[..2], [0, 1]);
+}
+
+#[test]
+fn negative_arguments_fail() {
+    assert!(matches!(run("r0 = math.sqr(#-4)\nend").0, StopReason::Fault(RuntimeError::InterruptError, _)));
+    assert!(matches!(run("r0 = math.pow(#2, #-1)\nend").0, StopReason::Fault(RuntimeError::InterruptError, _)));
+}
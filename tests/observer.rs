@@ -0,0 +1,75 @@
+//! An observer has to see every instruction, memory and stack access, interrupt call and
+//! fault, in order.
+
+#![cfg(all(feature = "compile", feature = "observer"))]
+
+use virtmach::{VirtMach, Observer, Fault, StopReason, RuntimeError};
+use virtmach::interrupts::{SoftInterrupt, Proc, BASE_INTERRUPT_DEFS};
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Instruction(usize),
+    MemoryWrite(usize, i16, i16),
+    Push(usize, i16),
+    Pop(usize, i16),
+    Enter(u8, i64),
+    Exit(u8, i64),
+    Fault(RuntimeError, usize)
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<Event>
+}
+
+impl Observer<i16> for Trace {
+    fn before_instruction(&mut self, pc: usize, _opcode: u8) { self.events.push(Event::Instruction(pc)); }
+    fn memory_write(&mut self, addr: usize, old: i16, new: i16) { self.events.push(Event::MemoryWrite(addr, old, new)); }
+    fn stack_push(&mut self, addr: usize, val: i16) { self.events.push(Event::Push(addr, val)); }
+    fn stack_pop(&mut self, addr: usize, val: i16) { self.events.push(Event::Pop(addr, val)); }
+    fn interrupt_enter(&mut self, slot: u8, function: i64) { self.events.push(Event::Enter(slot, function)); }
+    fn interrupt_exit(&mut self, slot: u8, function: i64) { self.events.push(Event::Exit(slot, function)); }
+    fn fault(&mut self, fault: &Fault) { self.events.push(Event::Fault(fault.error.clone(), fault.pc)); }
+}
+
+const LISTING: &str = "
+    reg r0
+    set #5
+    sto #2
+    psh r0
+    pop r1
+    r2 = proc.atom_size()
+    pop r3
+";
+
+#[test]
+fn hooks_report_execution() {
+    let program = <VirtMach>::compile("observer", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+    let mut trace = Trace::default();
+    let mut memory = [0i16;16];
+    let reason = {
+        let mut vm = <VirtMach>::new(&mut memory);
+        let mut interrupts: [&mut dyn SoftInterrupt; 1] = [&mut Proc {}];
+        vm.load_program(program.as_program(), &interrupts).unwrap();
+        vm.set_observer(Some(&mut trace));
+        vm.run(0, &mut interrupts)
+    };
+    let StopReason::Fault(RuntimeError::HeapUnderflow, pc) = reason else { panic!("expected an underflow, got {:?}", reason); };
+
+    let (instructions, effects): (Vec<_>, Vec<_>) = trace.events.into_iter().partition(|e| matches!(e, Event::Instruction(_)));
+    assert_eq!(instructions.len(), 9);
+    assert_eq!(instructions.first(), Some(&Event::Instruction(0)));
+    assert_eq!(instructions.last(), Some(&Event::Instruction(pc)));
+    assert_eq!(effects, [
+        Event::MemoryWrite(2, 0, 5),
+        Event::Push(15, 5),
+        Event::Pop(15, 5),
+        Event::Push(15, 1),
+        Event::Enter(0, 1),
+        Event::Pop(15, 1),
+        Event::Push(15, 2),
+        Event::Exit(0, 1),
+        Event::Pop(15, 2),
+        Event::Fault(RuntimeError::HeapUnderflow, pc)
+    ]);
+}
@@ -0,0 +1,87 @@
+//! `Random` has to draw the same sequence for the same seed, independent of other instances.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, HistoryEntry, StopReason};
+use virtmach::interrupts::{SoftInterrupt, Random, BASE_INTERRUPT_DEFS};
+
+const LISTING: &str = "
+    #req random
+    r0 = random.range(#-100, #100)
+    r1 = random.next()
+    _ = random.shuffle(#0, #8)
+    end
+";
+
+fn draw(random: &mut Random) -> ([i16;2], [i16;8]) {
+    let program = VirtMach::<i16>::compile("random", LISTING, BASE_INTERRUPT_DEFS).unwrap();
+    let mut memory = [0i16;16];
+    let registers = {
+        let mut vm = VirtMach::<i16>::new(&mut memory);
+        let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [random];
+        vm.load_program(program.as_program(), &interrupts).unwrap();
+        vm.memory[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        vm.run(0, &mut interrupts);
+        assert_eq!(vm.error, virtmach::RuntimeError::NoError);
+        [vm.registers[0], vm.registers[1]]
+    };
+    (registers, memory[..8].try_into().unwrap())
+}
+
+#[test]
+fn same_seed_same_sequence() {
+    let (mut a, mut b) = (Random::new(42), Random::new(42));
+    let first = draw(&mut a);
+    assert_eq!(draw(&mut b), first);
+    assert!((-100..=100).contains(&first.0[0]));
+
+    let mut shuffled = first.1;
+    shuffled.sort();
+    assert_eq!(shuffled, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+    assert_ne!(draw(&mut a), first);
+    assert_ne!(draw(&mut Random::new(43)), first);
+}
+
+#[test]
+fn range_spans_i64_atoms() {
+    let program = VirtMach::<i64>::compile("random", "r0 = random.range(#min, #max)\nr1 = random.range(#max, #-1)\nend", BASE_INTERRUPT_DEFS).unwrap();
+    let mut random = Random::new(1);
+    let mut memory = [0i64;8];
+    let mut vm = VirtMach::<i64>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i64>; 1] = [&mut random];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    assert!(vm.registers[1] >= -1);
+}
+
+#[test]
+fn shuffle_writes_through_the_vm() {
+    let program = VirtMach::<i16>::compile("random", "_ = random.shuffle(#0, #8)\nend", BASE_INTERRUPT_DEFS).unwrap();
+    let mut random = Random::new(7);
+    let mut history = [HistoryEntry::EMPTY;64];
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut random];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    vm.set_history(&mut history);
+    vm.memory[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    assert_ne!(vm.memory[..8], [0, 1, 2, 3, 4, 5, 6, 7]);
+
+    // stepping back over the `int` restores the unshuffled cells
+    while vm.step_back() {}
+    assert_eq!(vm.memory[..8], [0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn seeds_beyond_32_bits_are_truncated() {
+    let program = VirtMach::<i16>::compile("random", "r2 = random.next()\n_ = random.seed(#-1)\nr0 = random.next()\nr1 = random.range(#1, #6)\nend", BASE_INTERRUPT_DEFS).unwrap();
+    let mut random = Random::new(u64::MAX);
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    let mut interrupts: [&mut dyn SoftInterrupt<i16>; 1] = [&mut random];
+    vm.load_program(program.as_program(), &interrupts).unwrap();
+    assert_eq!(vm.run(0, &mut interrupts), StopReason::Ended);
+    assert!((1..=6).contains(&vm.registers[1]));
+}
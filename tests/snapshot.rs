@@ -0,0 +1,90 @@
+//! A restored snapshot has to continue exactly like the VM it was taken from.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, RuntimeError, StopReason};
+
+const LISTING: &str = "
+    reg r1
+    set #1
+loop:
+    reg r0
+    add #1
+    psh r0
+    pop r2
+    sto #1
+    reg r1
+    add r1
+    jmp loop
+";
+
+#[test]
+fn restored_vm_continues_identically() {
+    let program = VirtMach::<i16>::compile("snapshot", LISTING, &[]).unwrap();
+
+    let mut memory = [0i16;16];
+    let mut restored_memory = [0i16;16];
+    {
+        let mut vm = VirtMach::<i16>::new(&mut memory);
+        vm.load_program(program.as_program(), &[]).unwrap();
+        assert_eq!(vm.run(17, &mut []), StopReason::BudgetExhausted);
+
+        let mut buf = [0u8;128];
+        let len = vm.snapshot(&mut buf).unwrap();
+        assert_eq!(len, vm.snapshot_size());
+        vm.run(40, &mut []);
+
+        let mut restored = VirtMach::<i16>::new(&mut restored_memory);
+        restored.load_program(program.as_program(), &[]).unwrap();
+        restored.restore(&buf[..len]).unwrap();
+        restored.run(40, &mut []);
+
+        assert_eq!(restored.registers, vm.registers);
+        assert_eq!(restored.cycle_cnt, vm.cycle_cnt);
+        assert_eq!(restored.state, vm.state);
+    }
+    assert_eq!(restored_memory, memory);
+}
+
+#[test]
+fn snapshot_of_other_program_is_rejected() {
+    let program = VirtMach::<i16>::compile("snapshot", LISTING, &[]).unwrap();
+    let other = VirtMach::<i16>::compile("other", "reg r0\nend", &[]).unwrap();
+
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    vm.run(5, &mut []);
+    let mut buf = [0u8;128];
+    let len = vm.snapshot(&mut buf).unwrap();
+
+    vm.load_program(other.as_program(), &[]).unwrap();
+    assert_eq!(vm.restore(&buf[..len]), Err(RuntimeError::MismatchedProgram));
+    assert_eq!(vm.snapshot(&mut buf[..10]), Err(RuntimeError::BufferTooSmall));
+}
+
+#[test]
+fn snapshot_with_bad_irq_state_is_rejected() {
+    let program = VirtMach::<i16>::compile("snapshot", "#ent start\nhandler:\n    rti\nstart:\n    reg r0\n    ivt handler\n    ei\nloop:\n    jmp loop", &[]).unwrap();
+
+    let mut memory = [0i16;16];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    vm.run(5, &mut []);
+    let mut buf = [0u8;128];
+    let len = vm.snapshot(&mut buf).unwrap();
+
+    // a pending line beyond IRQ_MAX
+    let mut pending = buf;
+    pending[34] = 0x80;
+    assert_eq!(vm.restore(&pending[..len]), Err(RuntimeError::MalformedSnapshot));
+
+    // a handler of line 0 outside the code
+    let mut vector = buf;
+    vector[35..39].copy_from_slice(&1000u32.to_le_bytes());
+    assert_eq!(vm.restore(&vector[..len]), Err(RuntimeError::MalformedSnapshot));
+
+    assert_eq!(vm.restore(&buf[..len]), Ok(()));
+    assert!(vm.raise_irq(0));
+    assert_eq!(vm.run(10, &mut []), StopReason::BudgetExhausted);
+}
@@ -0,0 +1,100 @@
+//! `run` and `step` have to report why the VM stopped.
+
+#![cfg(feature = "compile")]
+
+use virtmach::{VirtMach, ProgramBuf, StopReason, RuntimeError, Runtime, Watchpoint, WatchTarget, WatchCondition};
+
+fn compile(listing: &str) -> ProgramBuf<i16> {
+    VirtMach::<i16>::compile("stop", listing, &[]).unwrap()
+}
+
+fn load<'a>(program: &'a ProgramBuf<i16>, memory: &'a mut [i16]) -> VirtMach<'a, i16> {
+    let mut vm = VirtMach::<i16>::new(memory);
+    vm.load_program(program.as_program(), &[]).unwrap();
+    vm
+}
+
+#[test]
+fn budget_exhausted_keeps_running() {
+    let mut memory = [0i16;8];
+    let program = compile("reg r0\nset #1\nend");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(1, &mut []), StopReason::BudgetExhausted);
+    assert_eq!(vm.step(&mut []), StopReason::BudgetExhausted);
+    assert_eq!(vm.state, Runtime::Run);
+    assert_eq!(vm.step(&mut []), StopReason::Ended);
+}
+
+#[test]
+fn halted_and_resumed() {
+    let mut memory = [0i16;8];
+    let program = compile("hlt\nreg r0\nset #1\nend");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::Halted);
+    assert_eq!(vm.stop_reason(), StopReason::Halted);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[0], 1);
+
+    vm.reset();
+    vm.run(1, &mut []);
+    vm.pause();
+    assert_eq!(vm.stop_reason(), StopReason::Halted);
+}
+
+#[test]
+fn ended_stays_ended() {
+    let mut memory = [0i16;8];
+    let program = compile("end\nreg r0\nset #1");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+    assert_eq!(vm.registers[0], 0);
+
+    let mut memory = [0i16;8];
+    let mut vm = VirtMach::<i16>::new(&mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+}
+
+#[test]
+fn breakpoints_from_host_and_program() {
+    let mut memory = [0i16;8];
+    let program = compile("brk\nreg r0\nset #1\nend");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+
+    vm.reset();
+    vm.set_halt_on_break(true);
+    assert_eq!(vm.run(0, &mut []), StopReason::Breakpoint(0));
+    assert!(vm.add_breakpoint(2));
+    assert_eq!(vm.run(0, &mut []), StopReason::Breakpoint(2));
+    assert_eq!(vm.run(0, &mut []), StopReason::Ended);
+}
+
+#[test]
+fn watchpoint_reports_id_and_position() {
+    let mut memory = [0i16;8];
+    let program = compile("reg r0\nset #1\nend");
+    let mut vm = load(&program, &mut memory);
+    let id = vm.add_watchpoint(Watchpoint { target: WatchTarget::Register(0), condition: WatchCondition::Write }).unwrap();
+    assert_eq!(vm.run(0, &mut []), StopReason::Watchpoint(id, 1));
+}
+
+#[test]
+fn fault_reports_error_and_position() {
+    let mut memory = [0i16;8];
+    let program = compile("reg r0\npop r0\nend");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::Fault(RuntimeError::HeapUnderflow, 1));
+    assert_eq!(vm.state, Runtime::Err);
+    assert_eq!(vm.run(0, &mut []), StopReason::Fault(RuntimeError::HeapUnderflow, 1));
+}
+
+#[test]
+fn waiting_for_interrupt() {
+    let mut memory = [0i16;8];
+    let program = compile("wfi\nreg r0\nset #1\nend");
+    let mut vm = load(&program, &mut memory);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.run(0, &mut []), StopReason::WaitingForInterrupt);
+    assert_eq!(vm.registers[0], 0);
+}